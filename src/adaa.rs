@@ -0,0 +1,345 @@
+// Антиалиасинг через первообразные (ADAA) для форм свертки
+// Все вычисления в f64: при большом Drive вход достигает 1e6, и в f32 разность соседних отсчетов теряется.
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::dsp::ShapeControls;
+use crate::engine::{AntiAlias, Waveform};
+use crate::wavetable::Wavetable;
+
+// Меньшая разность входа делает разделенные разности неустойчивыми, тогда запасная формула
+const TOLERANCE: f64 = 1.0e-5;
+
+// Один период [-2; 2) встроенных форм
+#[inline]
+fn wrap(x: f64) -> f64 {
+    x - 4.0 * ((x + 2.0) / 4.0).floor()
+}
+
+// Первообразные берутся от формы без среднего, чтобы они были периодическими при любом Drive;
+// среднее добавляется обратно как dc
+#[derive(Clone, Copy)]
+pub enum Shape<'a> {
+    Sine,
+    Triangle,
+    Saw,
+    Square,
+    Custom(&'a TableIntegrals),
+    // Смесь двух соседних кадров wavetable: (1 - t) * a + t * b
+    Morph(&'a TableIntegrals, &'a TableIntegrals, f64),
+    // Смесь двух форм для Waveform::Morph: (1 - t) * a + t * b
+    Blend(&'a Shape<'a>, &'a Shape<'a>, f64),
+}
+
+impl<'a> Shape<'a> {
+    // Смеси ссылаются на формы в этом кадре стека, поэтому через колбэк
+    #[inline]
+    pub fn with<R>(
+        waveform: Waveform,
+        table: &'a Wavetable,
+        controls: ShapeControls,
+        f: impl FnOnce(Shape) -> R,
+    ) -> R {
+        let a = table.shape(controls.position);
+        match table.slot_b() {
+            Some(b) if waveform.uses_table() && controls.table_morph > 0.0 => {
+                let b = b.shape(controls.position);
+                let custom = Shape::Blend(&a, &b, controls.table_morph as f64);
+                Self::with_custom(waveform, custom, controls.morph, f)
+            }
+            _ => Self::with_custom(waveform, a, controls.morph, f),
+        }
+    }
+
+    #[inline]
+    fn with_custom<R>(
+        waveform: Waveform,
+        custom: Shape<'_>,
+        morph: f32,
+        f: impl FnOnce(Shape) -> R,
+    ) -> R {
+        if waveform == Waveform::Morph {
+            let (a, b, t) = Waveform::morph_endpoints(morph);
+            let a = Self::from_waveform(a, custom);
+            let b = Self::from_waveform(b, custom);
+            f(Shape::Blend(&a, &b, t as f64))
+        } else {
+            f(Self::from_waveform(waveform, custom))
+        }
+    }
+
+    fn from_waveform<'s>(waveform: Waveform, custom: Shape<'s>) -> Shape<'s> {
+        match waveform {
+            Waveform::Sine => Shape::Sine,
+            Waveform::Triangle => Shape::Triangle,
+            Waveform::Saw => Shape::Saw,
+            Waveform::Square => Shape::Square,
+            Waveform::Custom => custom,
+            Waveform::Morph => unreachable!("the morph is split into two shapes by Shape::with"),
+        }
+    }
+
+    #[inline]
+    fn value(self, x: f64) -> f64 {
+        match self {
+            Shape::Sine => (x * FRAC_PI_2).sin(),
+            Shape::Triangle => {
+                let t = wrap(x);
+                if t > 1.0 {
+                    2.0 - t
+                } else if t < -1.0 {
+                    -2.0 - t
+                } else {
+                    t
+                }
+            }
+            Shape::Saw => wrap(x) / 2.0,
+            Shape::Square => wrap(x).signum(),
+            Shape::Custom(integrals) => integrals.value(x),
+            Shape::Morph(a, b, t) => a.value(x) * (1.0 - t) + b.value(x) * t,
+            Shape::Blend(a, b, t) => a.value(x) * (1.0 - t) + b.value(x) * t,
+        }
+    }
+
+    #[inline]
+    fn dc(self) -> f64 {
+        match self {
+            Shape::Custom(integrals) => integrals.mean,
+            Shape::Morph(a, b, t) => a.mean * (1.0 - t) + b.mean * t,
+            Shape::Blend(a, b, t) => a.dc() * (1.0 - t) + b.dc() * t,
+            _ => 0.0,
+        }
+    }
+
+    // Первая первообразная с нулевым средним за период
+    #[inline]
+    fn ad1(self, x: f64) -> f64 {
+        match self {
+            Shape::Sine => -2.0 / PI * (x * FRAC_PI_2).cos(),
+            Shape::Triangle => {
+                let t = wrap(x);
+                let a = t.abs();
+                if a <= 1.0 {
+                    t * t / 2.0 - 0.5
+                } else {
+                    2.0 * a - t * t / 2.0 - 1.5
+                }
+            }
+            Shape::Saw => {
+                let t = wrap(x);
+                t * t / 4.0 - 1.0 / 3.0
+            }
+            Shape::Square => wrap(x).abs() - 1.0,
+            Shape::Custom(integrals) => integrals.ad1(x),
+            Shape::Morph(a, b, t) => a.ad1(x) * (1.0 - t) + b.ad1(x) * t,
+            Shape::Blend(a, b, t) => a.ad1(x) * (1.0 - t) + b.ad1(x) * t,
+        }
+    }
+
+    // Вторая первообразная, периодическая, потому что у ad1 нулевое среднее
+    #[inline]
+    fn ad2(self, x: f64) -> f64 {
+        match self {
+            Shape::Sine => -4.0 / (PI * PI) * (x * FRAC_PI_2).sin(),
+            Shape::Triangle => {
+                let t = wrap(x);
+                let a = t.abs();
+                let p = if a <= 1.0 {
+                    a * a * a / 6.0 - a / 2.0
+                } else {
+                    a * a - a * a * a / 6.0 - 1.5 * a + 1.0 / 3.0
+                };
+                p * t.signum()
+            }
+            Shape::Saw => {
+                let t = wrap(x);
+                t * t * t / 12.0 - t / 3.0
+            }
+            Shape::Square => {
+                let t = wrap(x);
+                let a = t.abs();
+                (a * a / 2.0 - a) * t.signum()
+            }
+            Shape::Custom(integrals) => integrals.ad2(x),
+            Shape::Morph(a, b, t) => a.ad2(x) * (1.0 - t) + b.ad2(x) * t,
+            Shape::Blend(a, b, t) => a.ad2(x) * (1.0 - t) + b.ad2(x) * t,
+        }
+    }
+}
+
+// Интегралы своей таблицы, считаются при загрузке вместе с нулями.
+// Таблица - та же ломаная с периодом 4, что играет utils::lookup_custom
+pub struct TableIntegrals {
+    samples: Vec<f64>,
+    g1: Vec<f64>,
+    g2: Vec<f64>,
+    mean: f64,
+    g1_mean: f64,
+    step: f64,
+}
+
+impl TableIntegrals {
+    pub fn new(table: &[f32]) -> Self {
+        let len = table.len();
+        if len < 2 {
+            // Как и lookup_custom, слишком короткая таблица дает тишину
+            return Self {
+                samples: Vec::new(),
+                g1: Vec::new(),
+                g2: Vec::new(),
+                mean: 0.0,
+                g1_mean: 0.0,
+                step: 0.0,
+            };
+        }
+
+        let samples = table.iter().map(|&s| s as f64).collect::<Vec<_>>();
+        let step = 4.0 / (len - 1) as f64;
+        let mean = samples
+            .windows(2)
+            .map(|w| (w[0] + w[1]) * 0.5 * step)
+            .sum::<f64>()
+            / 4.0;
+
+        // Интегрируем кусочно-линейную функцию без постоянной составляющей точно, отрезок за отрезком
+        let mut g1 = Vec::with_capacity(len);
+        let mut g2 = Vec::with_capacity(len);
+        let (mut acc1, mut acc2) = (0.0, 0.0);
+        for k in 0..len {
+            g1.push(acc1);
+            g2.push(acc2);
+            if k + 1 < len {
+                let a = samples[k] - mean;
+                let b = samples[k + 1] - mean;
+                acc2 += acc1 * step + a * step * step / 2.0 + (b - a) * step * step / 6.0;
+                acc1 += (a + b) * 0.5 * step;
+            }
+        }
+
+        Self {
+            samples,
+            g1,
+            g2,
+            mean,
+            g1_mean: acc2 / 4.0,
+            step,
+        }
+    }
+
+    // Номер отрезка и смещение в нем для x в [0; 4)
+    #[inline]
+    fn locate(&self, t: f64) -> (usize, f64) {
+        let k = ((t / self.step) as usize).min(self.samples.len() - 2);
+        (k, t - k as f64 * self.step)
+    }
+
+    #[inline]
+    fn value(&self, x: f64) -> f64 {
+        if self.samples.is_empty() || !x.is_finite() {
+            return 0.0;
+        }
+        let (k, u) = self.locate(x.rem_euclid(4.0));
+        self.samples[k] + (self.samples[k + 1] - self.samples[k]) * u / self.step
+    }
+
+    #[inline]
+    fn ad1(&self, x: f64) -> f64 {
+        if self.samples.is_empty() || !x.is_finite() {
+            return 0.0;
+        }
+        let (k, u) = self.locate(x.rem_euclid(4.0));
+        let a = self.samples[k] - self.mean;
+        let b = self.samples[k + 1] - self.mean;
+        self.g1[k] + a * u + (b - a) * u * u / (2.0 * self.step) - self.g1_mean
+    }
+
+    #[inline]
+    fn ad2(&self, x: f64) -> f64 {
+        if self.samples.is_empty() || !x.is_finite() {
+            return 0.0;
+        }
+        let t = x.rem_euclid(4.0);
+        let (k, u) = self.locate(t);
+        let a = self.samples[k] - self.mean;
+        let b = self.samples[k + 1] - self.mean;
+        self.g2[k] + self.g1[k] * u + a * u * u / 2.0 + (b - a) * u * u * u / (6.0 * self.step)
+            - self.g1_mean * t
+    }
+}
+
+// История ADAA одного канала
+#[derive(Clone, Copy, Default)]
+pub struct AdaaState {
+    x1: f64,
+    x2: f64,
+    d2: f64,
+    dry1: f32,
+}
+
+impl AdaaState {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn first_order(&mut self, shape: Shape, x: f64) -> f64 {
+        let x1 = self.x1;
+        self.x2 = x1;
+        self.x1 = x;
+
+        if (x - x1).abs() < TOLERANCE {
+            shape.value((x + x1) / 2.0)
+        } else {
+            shape.dc() + (shape.ad1(x) - shape.ad1(x1)) / (x - x1)
+        }
+    }
+
+    pub fn second_order(&mut self, shape: Shape, x: f64) -> f64 {
+        let d1 = Self::first_divided_difference(shape, x, self.x1);
+        let y = if (x - self.x2).abs() < TOLERANCE {
+            self.fallback(shape, x)
+        } else {
+            shape.dc() + 2.0 / (x - self.x2) * (d1 - self.d2)
+        };
+
+        self.d2 = d1;
+        self.x2 = self.x1;
+        self.x1 = x;
+        y
+    }
+
+    // Разделенная разность для другой формы: второй порядок продолжается без скачка
+    pub fn rebase(&mut self, shape: Shape) {
+        self.d2 = Self::first_divided_difference(shape, self.x1, self.x2);
+    }
+
+    // Сухой сигнал задерживается на те же полотсчета на порядок, что ADAA добавляет мокрому
+    pub fn align_dry(&mut self, order: AntiAlias, dry: f32) -> f32 {
+        let dry1 = self.dry1;
+        self.dry1 = dry;
+        match order {
+            AntiAlias::Off => dry,
+            AntiAlias::FirstOrder => (dry + dry1) / 2.0,
+            AntiAlias::SecondOrder => dry1,
+        }
+    }
+
+    #[inline]
+    fn first_divided_difference(shape: Shape, x0: f64, x1: f64) -> f64 {
+        if (x0 - x1).abs() < TOLERANCE {
+            shape.ad1((x0 + x1) / 2.0)
+        } else {
+            (shape.ad2(x0) - shape.ad2(x1)) / (x0 - x1)
+        }
+    }
+
+    #[inline]
+    fn fallback(&self, shape: Shape, x: f64) -> f64 {
+        let x_bar = (x + self.x2) / 2.0;
+        let delta = x_bar - self.x1;
+        if delta.abs() < TOLERANCE {
+            shape.value((x_bar + self.x1) / 2.0)
+        } else {
+            shape.dc()
+                + 2.0 / delta * (shape.ad1(x_bar) + (shape.ad2(self.x1) - shape.ad2(x_bar)) / delta)
+        }
+    }
+}
@@ -0,0 +1,187 @@
+// Оффлайн-рендер: wavefolder_render <input.wav> <output.wav> [опции]
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use wavefolder::render::{Normalization, RenderOptions, TableChannels, render_file};
+
+const USAGE: &str = "\
+Usage: wavefolder_render <input.wav> <output.wav> [options]
+
+Options:
+  --table <file>            custom table (wav, flac, ogg, mp3, wt, csv, txt or f32), implies --waveform custom
+  --table-b <file>          table of slot B, loaded with the same options, implies --waveform custom
+  --table-morph <0..1>      blend from the table to slot B (default: 0)
+  --align-crossings         warp slot B so that its zero crossings meet those of the table
+  --frame-size <samples>    frame size of a wavetable bank (default: from the file)
+  --table-channels <mode>   mix, stereo (left/right tables) or a channel number from 1
+                            (default: mix)
+  --normalize <mode>        table normalization: none, peak, rms, dc-peak or symmetric
+                            (default: peak)
+  --position <0..1>         position inside a wavetable bank (default: 0)
+  --waveform <name>         sine, triangle, saw, square, custom or morph (default: triangle)
+  --shape <0..4>            morph position: 0 sine, 1 triangle, 2 saw, 3 square, 4 custom table,
+                            implies --waveform morph (default: 0)
+  --interpolation <name>    table interpolation: nearest, linear, hermite, catmull-rom,
+                            lagrange4, lagrange6 or sinc (default: linear)
+  --band-limit              use band-limited levels of the custom table at high drive
+  --drive <dB>              -60..120 (default: 0)
+  --phase <deg>             0..360 (default: 0)
+  --dry-wet <0..1>          (default: 1)
+  --anti-alias <mode>       off, adaa1 or adaa2 (default: off)
+  --oversampling <factor>   1, 2, 4, 8 or 16 (default: 1)
+  --quality <name>          low, medium or high (default: medium)
+  --spread <deg>            0..180 (default: 0)
+  --mid-side                fold channel pairs as mid/side";
+
+fn parse_args(args: &[String]) -> Result<RenderOptions, String> {
+    let mut options = RenderOptions::default();
+    let mut positional = Vec::new();
+    let mut waveform_set = false;
+    let mut shape_set = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}", arg))
+        };
+        let parse_f32 = |value: &String| {
+            value
+                .parse::<f32>()
+                .map_err(|_| format!("Invalid value for {}: {}", arg, value))
+        };
+
+        match arg.as_str() {
+            "--table" => options.table = Some(PathBuf::from(value()?)),
+            "--table-b" => options.table_b = Some(PathBuf::from(value()?)),
+            "--table-morph" => options.table_morph = parse_f32(value()?)?.clamp(0.0, 1.0),
+            "--align-crossings" => options.align_crossings = true,
+            "--waveform" => {
+                waveform_set = true;
+                options.waveform = match value()?.as_str() {
+                    "sine" => 0,
+                    "triangle" => 1,
+                    "saw" => 2,
+                    "square" => 3,
+                    "custom" => 4,
+                    "morph" => 5,
+                    other => return Err(format!("Unknown waveform: {}", other)),
+                }
+            }
+            "--interpolation" => {
+                options.interpolation = match value()?.as_str() {
+                    "nearest" => 0,
+                    "linear" => 1,
+                    "hermite" => 2,
+                    "catmull-rom" => 3,
+                    "lagrange4" => 4,
+                    "lagrange6" => 5,
+                    "sinc" => 6,
+                    other => return Err(format!("Unknown interpolation: {}", other)),
+                }
+            }
+            "--drive" => options.drive = parse_f32(value()?)?.clamp(-60.0, 120.0),
+            "--phase" => options.phase = parse_f32(value()?)?.clamp(0.0, 360.0),
+            "--dry-wet" => options.dry_wet = parse_f32(value()?)?.clamp(0.0, 1.0),
+            "--frame-size" => {
+                let value = value()?;
+                options.frame_size = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for {}: {}", arg, value))?
+            }
+            "--table-channels" => {
+                options.table_channels = match value()?.as_str() {
+                    "mix" => TableChannels::Mix,
+                    "stereo" => TableChannels::Stereo,
+                    other => match other.parse::<usize>() {
+                        Ok(channel) if channel > 0 => TableChannels::Channel(channel - 1),
+                        _ => return Err(format!("Invalid value for {}: {}", arg, other)),
+                    },
+                }
+            }
+            "--normalize" => {
+                options.normalization = match value()?.as_str() {
+                    "none" => Normalization::None,
+                    "peak" => Normalization::Peak,
+                    "rms" => Normalization::Rms,
+                    "dc-peak" => Normalization::DcPeak,
+                    "symmetric" => Normalization::SymmetricPeak,
+                    other => return Err(format!("Unknown normalization: {}", other)),
+                }
+            }
+            "--shape" => {
+                shape_set = true;
+                options.shape = parse_f32(value()?)?.clamp(0.0, 4.0);
+            }
+            "--position" => options.table_position = parse_f32(value()?)?.clamp(0.0, 1.0),
+            "--spread" => options.spread = parse_f32(value()?)?.clamp(0.0, 180.0),
+            "--anti-alias" => {
+                options.anti_alias = match value()?.as_str() {
+                    "off" => 0,
+                    "adaa1" => 1,
+                    "adaa2" => 2,
+                    other => return Err(format!("Unknown anti-alias mode: {}", other)),
+                }
+            }
+            "--oversampling" => {
+                options.oversampling = match value()?.as_str() {
+                    "1" => 0,
+                    "2" => 1,
+                    "4" => 2,
+                    "8" => 3,
+                    "16" => 4,
+                    other => return Err(format!("Unsupported oversampling factor: {}", other)),
+                }
+            }
+            "--quality" => {
+                options.oversampling_quality = match value()?.as_str() {
+                    "low" => 0,
+                    "medium" => 1,
+                    "high" => 2,
+                    other => return Err(format!("Unknown quality: {}", other)),
+                }
+            }
+            "--mid-side" => options.mid_side = true,
+            "--band-limit" => options.band_limit = true,
+            other if other.starts_with("--") => return Err(format!("Unknown option: {}", other)),
+            other => positional.push(other),
+        }
+    }
+
+    let [input, output] = positional[..] else {
+        return Err("Expected an input and an output file".to_string());
+    };
+    options.input = PathBuf::from(input);
+    options.output = PathBuf::from(output);
+    if shape_set && !waveform_set {
+        options.waveform = 5;
+    } else if (options.table.is_some() || options.table_b.is_some()) && !waveform_set {
+        options.waveform = 4;
+    }
+
+    Ok(options)
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match render_file(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Render failed: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
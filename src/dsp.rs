@@ -0,0 +1,387 @@
+use crate::adaa;
+use crate::engine::{AntiAlias, Interpolation, Waveform};
+use crate::oversampling;
+use crate::smoothing::Smoother;
+use crate::utils;
+use crate::wavetable::Wavetable;
+
+// Размер внутреннего блока, на который режется буфер хоста
+pub const MAX_BLOCK_SIZE: usize = 64;
+
+// Drive в дБ, Phase в градусах, Dry/Wet 0..1
+pub struct ParamBlock {
+    pub gain: [f32; MAX_BLOCK_SIZE],
+    pub phase: [f32; MAX_BLOCK_SIZE],
+    pub dw: [f32; MAX_BLOCK_SIZE],
+}
+
+impl ParamBlock {
+    pub fn new() -> Self {
+        Self {
+            gain: [0.0; MAX_BLOCK_SIZE],
+            phase: [0.0; MAX_BLOCK_SIZE],
+            dw: [0.0; MAX_BLOCK_SIZE],
+        }
+    }
+
+    pub fn fill(
+        &mut self,
+        gain: &mut Smoother,
+        phase: &mut Smoother,
+        dw: &mut Smoother,
+        block_len: usize,
+    ) {
+        gain.fill(&mut self.gain[..block_len]);
+        phase.fill(&mut self.phase[..block_len]);
+        dw.fill(&mut self.dw[..block_len]);
+    }
+
+    fn prepare(&mut self, block_len: usize) {
+        for i in 0..block_len {
+            self.gain[i] = utils::db_to_gain(self.gain[i]);
+            self.phase[i] /= 90.0;
+        }
+    }
+}
+
+// Общие для всех каналов значения; fade - вес новой формы при кроссфейде, 1 без него
+pub struct SharedBlock {
+    pub spread: [f32; MAX_BLOCK_SIZE],
+    pub position: [f32; MAX_BLOCK_SIZE],
+    pub morph: [f32; MAX_BLOCK_SIZE],
+    pub table_morph: [f32; MAX_BLOCK_SIZE],
+    pub fade: [f32; MAX_BLOCK_SIZE],
+}
+
+impl SharedBlock {
+    pub fn new() -> Self {
+        Self {
+            spread: [0.0; MAX_BLOCK_SIZE],
+            position: [0.0; MAX_BLOCK_SIZE],
+            morph: [0.0; MAX_BLOCK_SIZE],
+            table_morph: [0.0; MAX_BLOCK_SIZE],
+            fade: [1.0; MAX_BLOCK_SIZE],
+        }
+    }
+
+    pub fn fill(
+        &mut self,
+        spread: &mut Smoother,
+        position: &mut Smoother,
+        morph: &mut Smoother,
+        table_morph: &mut Smoother,
+        fade: &mut Smoother,
+        block_len: usize,
+    ) {
+        spread.fill(&mut self.spread[..block_len]);
+        position.fill(&mut self.position[..block_len]);
+        morph.fill(&mut self.morph[..block_len]);
+        table_morph.fill(&mut self.table_morph[..block_len]);
+        fade.fill(&mut self.fade[..block_len]);
+    }
+
+    fn controls(&self, index: usize) -> ShapeControls {
+        ShapeControls {
+            position: self.position[index],
+            morph: self.morph[index],
+            table_morph: self.table_morph[index],
+        }
+    }
+
+    fn prepare(&mut self, block_len: usize) {
+        // Каждый канал пары сдвигается на половину разноса
+        for spread in &mut self.spread[..block_len] {
+            *spread /= 180.0;
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ShapeControls {
+    pub position: f32,
+    pub morph: f32,
+    pub table_morph: f32,
+}
+
+// Все, что постоянно в пределах буфера
+#[derive(Clone, Copy)]
+pub struct FoldSettings {
+    pub waveform: Waveform,
+    // Форма, из которой идет кроссфейд
+    pub fade_waveform: Waveform,
+    pub interpolation: Interpolation,
+    pub band_limit: bool,
+    pub anti_alias: AntiAlias,
+    pub os_stages: usize,
+    pub os_quality: usize,
+    pub linked: bool,
+    pub mid_side: bool,
+}
+
+impl FoldSettings {
+    pub fn latency(&self) -> u32 {
+        oversampling::latency_samples(self.os_stages, self.os_quality)
+    }
+}
+
+// Цикл свертки WaveFolderEngine со всем состоянием каналов
+pub struct Folder {
+    oversamplers: Vec<oversampling::Oversampler>,
+    adaa_states: Vec<adaa::AdaaState>,
+    // История ADAA старой формы на время кроссфейда
+    fade_adaa_states: Vec<adaa::AdaaState>,
+    // Кроссфейд начат, но состояния ADAA еще не разделены
+    fade_pending: bool,
+    // Предыдущий вход таблицы каждого канала, по нему считается скорость прохода по таблице
+    last_inputs: Vec<f32>,
+    anti_alias: AntiAlias,
+    // [0] - основной набор параметров, [1] - для правых каналов (или Side) в несвязанном режиме
+    param_blocks: [ParamBlock; 2],
+    shared_block: SharedBlock,
+}
+
+impl Folder {
+    pub fn new(num_channels: usize) -> Self {
+        Self {
+            oversamplers: (0..num_channels)
+                .map(|_| oversampling::Oversampler::new(MAX_BLOCK_SIZE))
+                .collect(),
+            adaa_states: vec![adaa::AdaaState::default(); num_channels],
+            fade_adaa_states: vec![adaa::AdaaState::default(); num_channels],
+            fade_pending: false,
+            last_inputs: vec![0.0; num_channels],
+            anti_alias: AntiAlias::Off,
+            param_blocks: [ParamBlock::new(), ParamBlock::new()],
+            shared_block: SharedBlock::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.oversamplers
+            .iter_mut()
+            .for_each(oversampling::Oversampler::reset);
+        self.adaa_states.iter_mut().for_each(adaa::AdaaState::reset);
+        self.fade_adaa_states
+            .iter_mut()
+            .for_each(adaa::AdaaState::reset);
+        self.fade_pending = false;
+        self.last_inputs.fill(0.0);
+    }
+
+    // У старой формы своя история ADAA, обе стороны кроссфейда непрерывны
+    pub fn start_fade(&mut self) {
+        self.fade_pending = true;
+    }
+
+    // fill_params вызывается на каждый внутренний блок и пишет значения обоих наборов и общие
+    pub fn process<F>(
+        &mut self,
+        channels: &mut [&mut [f32]],
+        num_samples: usize,
+        settings: &FoldSettings,
+        custom_table: &Wavetable,
+        fade_table: &Wavetable,
+        mut fill_params: F,
+    ) where
+        F: FnMut(&mut [ParamBlock; 2], &mut SharedBlock, usize),
+    {
+        let FoldSettings {
+            waveform,
+            fade_waveform,
+            interpolation: _,
+            band_limit: _,
+            anti_alias,
+            os_stages,
+            os_quality,
+            linked,
+            mid_side,
+        } = *settings;
+        let num_channels = channels.len();
+
+        if anti_alias != self.anti_alias {
+            // История ADAA другого порядка не годится, начинаем заново
+            self.anti_alias = anti_alias;
+            self.adaa_states.iter_mut().for_each(adaa::AdaaState::reset);
+            self.fade_adaa_states
+                .iter_mut()
+                .for_each(adaa::AdaaState::reset);
+        }
+
+        // В режиме Mid/Side каждая пара каналов превращается в M (четный) и S (нечетный)
+        if mid_side {
+            for pair in channels.chunks_exact_mut(2) {
+                if let [left, right] = pair {
+                    utils::encode_mid_side(left, right);
+                }
+            }
+        }
+
+        let mut block_start = 0;
+        while block_start < num_samples {
+            let block_len = (num_samples - block_start).min(MAX_BLOCK_SIZE);
+
+            // Значения параметров считаем один раз на блок, общие для всех каналов
+            fill_params(&mut self.param_blocks, &mut self.shared_block, block_len);
+            for param_block in self.param_blocks.iter_mut() {
+                param_block.prepare(block_len);
+            }
+            self.shared_block.prepare(block_len);
+            let shared_block = &self.shared_block;
+            // Вес новой формы только растет, так что кроссфейд идет, пока первый отсчет меньше 1
+            let fading = shared_block.fade[0] < 1.0;
+            let fade_pending = std::mem::take(&mut self.fade_pending);
+
+            for (
+                channel_index,
+                ((((channel_samples, oversampler), adaa_state), fade_adaa_state), last_input),
+            ) in channels
+                .iter_mut()
+                .zip(self.oversamplers.iter_mut())
+                .zip(self.adaa_states.iter_mut())
+                .zip(self.fade_adaa_states.iter_mut())
+                .zip(self.last_inputs.iter_mut())
+                .enumerate()
+            {
+                // M и S всегда со своими наборами, связка касается только L/R
+                let param_block = if (linked && !mid_side) || channel_index % 2 == 0 {
+                    &self.param_blocks[0]
+                } else {
+                    &self.param_blocks[1]
+                };
+                // Левый канал пары сдвигаем назад, правый вперед; моно не трогаем
+                let spread_sign = if num_channels < 2 {
+                    0.0
+                } else if channel_index % 2 == 0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                // У стереотаблицы свой канал таблицы для каждого канала пары
+                let custom_table = custom_table.channel(channel_index);
+                let fade_table = fade_table.channel(channel_index);
+                let block = &mut channel_samples[block_start..block_start + block_len];
+
+                if fade_pending {
+                    // Старая форма продолжает со своей историей, новая - с пересчитанной под нее
+                    *fade_adaa_state = *adaa_state;
+                    adaa::Shape::with(waveform, custom_table, shared_block.controls(0), |shape| {
+                        adaa_state.rebase(shape)
+                    });
+                }
+
+                // Сухой сигнал тоже проходит через фильтры, чтобы совпадала задержка
+                oversampler.process(block, os_stages, os_quality, |samples| {
+                    for (i, sample) in samples.iter_mut().enumerate() {
+                        let base = i >> os_stages;
+                        let controls = shared_block.controls(base);
+
+                        let wet = if anti_alias == AntiAlias::Off {
+                            let input_folded = *sample * param_block.gain[base]
+                                + param_block.phase[base]
+                                + spread_sign * shared_block.spread[base];
+                            let slope = (input_folded - *last_input).abs();
+                            *last_input = input_folded;
+                            let shape = |waveform, table: &Wavetable| {
+                                fold(waveform, table, controls, input_folded, slope, settings)
+                            };
+                            let wet = shape(waveform, custom_table);
+                            if fading {
+                                let old = shape(fade_waveform, fade_table);
+                                old + (wet - old) * shared_block.fade[base]
+                            } else {
+                                wet
+                            }
+                        } else {
+                            let input_folded = *sample as f64 * param_block.gain[base] as f64
+                                + param_block.phase[base] as f64
+                                + (spread_sign * shared_block.spread[base]) as f64;
+                            let shape = |waveform, table, state: &mut adaa::AdaaState| {
+                                adaa::Shape::with(waveform, table, controls, |shape| {
+                                    if anti_alias == AntiAlias::FirstOrder {
+                                        state.first_order(shape, input_folded)
+                                    } else {
+                                        state.second_order(shape, input_folded)
+                                    }
+                                })
+                            };
+                            let wet = shape(waveform, custom_table, adaa_state);
+                            let wet = if fading {
+                                let old = shape(fade_waveform, fade_table, fade_adaa_state);
+                                old + (wet - old) * shared_block.fade[base] as f64
+                            } else {
+                                wet
+                            };
+                            wet as f32
+                        };
+
+                        let dry = adaa_state.align_dry(anti_alias, *sample);
+                        *sample = utils::xfader(dry, wet, param_block.dw[base]);
+                    }
+                });
+            }
+
+            block_start += block_len;
+        }
+
+        if mid_side {
+            for pair in channels.chunks_exact_mut(2) {
+                if let [mid, side] = pair {
+                    utils::decode_mid_side(mid, side);
+                }
+            }
+        }
+    }
+}
+
+// slope - насколько сдвинулся вход с прошлого отсчета, по нему выбирается мип-уровень
+#[inline]
+fn fold(
+    waveform: Waveform,
+    custom_table: &Wavetable,
+    controls: ShapeControls,
+    input_folded: f32,
+    slope: f32,
+    settings: &FoldSettings,
+) -> f32 {
+    let lookup = |table: &Wavetable| {
+        if settings.band_limit {
+            table.lookup_band_limited(
+                controls.position,
+                input_folded,
+                slope,
+                settings.interpolation,
+            )
+        } else {
+            table.lookup(controls.position, input_folded, settings.interpolation)
+        }
+    };
+
+    match waveform {
+        Waveform::Sine => utils::sine(input_folded),
+        Waveform::Triangle => utils::triangle(input_folded),
+        Waveform::Saw => utils::saw(input_folded),
+        Waveform::Square => utils::meander(input_folded),
+        Waveform::Custom => {
+            let a = lookup(custom_table);
+            match custom_table.slot_b() {
+                Some(b) if controls.table_morph > 0.0 => a + (lookup(b) - a) * controls.table_morph,
+                _ => a,
+            }
+        }
+        Waveform::Morph => {
+            let (a, b, t) = Waveform::morph_endpoints(controls.morph);
+            let fold = |waveform| {
+                fold(
+                    waveform,
+                    custom_table,
+                    controls,
+                    input_folded,
+                    slope,
+                    settings,
+                )
+            };
+            let a = fold(a);
+            a + (fold(b) - a) * t
+        }
+    }
+}
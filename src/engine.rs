@@ -0,0 +1,508 @@
+// DSP без хоста: WF::process только передает сюда параметры, движок можно тестировать и встраивать
+use std::sync::Arc;
+
+use crate::dsp::{FoldSettings, Folder};
+use crate::smoothing::Smoother;
+pub use crate::smoothing::{Smoothing, SmoothingSettings, SmoothingStyle};
+pub use crate::table_handoff::TableHandoff;
+pub use crate::wavetable::Wavetable;
+
+// Время кроссфейда при смене формы или таблицы по умолчанию
+pub const DEFAULT_CROSSFADE_MS: f32 = 20.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Waveform {
+    Sine,
+    Triangle,
+    Saw,
+    Square,
+    Custom,
+    // Плавный переход по MORPH_SHAPES, положение задает set_morph
+    Morph,
+}
+
+// Формы Morph по порядку, значение морфа - индекс в этом списке
+pub const MORPH_SHAPES: [Waveform; 5] = [
+    Waveform::Sine,
+    Waveform::Triangle,
+    Waveform::Saw,
+    Waveform::Square,
+    Waveform::Custom,
+];
+
+impl Waveform {
+    pub fn from_index(index: i32) -> Self {
+        match index {
+            0 => Waveform::Sine,
+            1 => Waveform::Triangle,
+            2 => Waveform::Saw,
+            3 => Waveform::Square,
+            4 => Waveform::Custom,
+            5 => Waveform::Morph,
+            _ => Waveform::Sine,
+        }
+    }
+
+    // Две соседние формы и вес второй для значения морфа 0..=4
+    #[inline]
+    pub fn morph_endpoints(morph: f32) -> (Waveform, Waveform, f32) {
+        let morph = morph.clamp(0.0, (MORPH_SHAPES.len() - 1) as f32);
+        let index = (morph as usize).min(MORPH_SHAPES.len() - 2);
+        (
+            MORPH_SHAPES[index],
+            MORPH_SHAPES[index + 1],
+            morph - index as f32,
+        )
+    }
+
+    pub fn uses_table(self) -> bool {
+        matches!(self, Waveform::Custom | Waveform::Morph)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AntiAlias {
+    Off,
+    FirstOrder,
+    SecondOrder,
+}
+
+impl AntiAlias {
+    pub fn from_index(index: i32) -> Self {
+        match index {
+            1 => AntiAlias::FirstOrder,
+            2 => AntiAlias::SecondOrder,
+            _ => AntiAlias::Off,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Oversampling {
+    X1,
+    X2,
+    X4,
+    X8,
+    X16,
+}
+
+impl Oversampling {
+    // Значение параметра oversampling - число ступеней 2x
+    pub fn from_index(index: i32) -> Self {
+        match index {
+            1 => Oversampling::X2,
+            2 => Oversampling::X4,
+            3 => Oversampling::X8,
+            4 => Oversampling::X16,
+            _ => Oversampling::X1,
+        }
+    }
+
+    fn stages(self) -> usize {
+        match self {
+            Oversampling::X1 => 0,
+            Oversampling::X2 => 1,
+            Oversampling::X4 => 2,
+            Oversampling::X8 => 3,
+            Oversampling::X16 => 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OversamplingQuality {
+    Low,
+    Medium,
+    High,
+}
+
+impl OversamplingQuality {
+    pub fn from_index(index: i32) -> Self {
+        match index {
+            0 => OversamplingQuality::Low,
+            2 => OversamplingQuality::High,
+            _ => OversamplingQuality::Medium,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            OversamplingQuality::Low => 0,
+            OversamplingQuality::Medium => 1,
+            OversamplingQuality::High => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Interpolation {
+    Nearest,
+    Linear,
+    Hermite,
+    CatmullRom,
+    Lagrange4,
+    Lagrange6,
+    Sinc,
+}
+
+impl Interpolation {
+    pub fn from_index(index: i32) -> Self {
+        match index {
+            0 => Interpolation::Nearest,
+            2 => Interpolation::Hermite,
+            3 => Interpolation::CatmullRom,
+            4 => Interpolation::Lagrange4,
+            5 => Interpolation::Lagrange6,
+            6 => Interpolation::Sinc,
+            _ => Interpolation::Linear,
+        }
+    }
+}
+
+// Сглаживатели Drive, Phase и Dry/Wet одного набора параметров
+struct SmoothedSet {
+    drive: Smoother,
+    phase: Smoother,
+    dry_wet: Smoother,
+}
+
+impl SmoothedSet {
+    fn new() -> Self {
+        Self {
+            drive: Smoother::new(0.0),
+            phase: Smoother::new(0.0),
+            dry_wet: Smoother::new(1.0),
+        }
+    }
+
+    fn smoothers(&mut self) -> [&mut Smoother; 3] {
+        [&mut self.drive, &mut self.phase, &mut self.dry_wet]
+    }
+}
+
+pub struct WaveFolderEngine {
+    folder: Folder,
+    num_channels: usize,
+    table: Arc<Wavetable>,
+    // Таблица, из которой идет кроссфейд; освобождается только через TableHandoff
+    previous_table: Arc<Wavetable>,
+    sample_rate: f32,
+    smoothing: SmoothingSettings,
+    crossfade_ms: f32,
+    waveform: Waveform,
+    // Форма, выбранная во время кроссфейда; кроссфейд к ней начнется после текущего
+    next_waveform: Waveform,
+    // Форма, из которой идет кроссфейд, и читает ли она предыдущую таблицу
+    fade_waveform: Waveform,
+    fade_from_previous: bool,
+    // Вес новой формы, от 0 до 1
+    fade: Smoother,
+    interpolation: Interpolation,
+    band_limit: bool,
+    anti_alias: AntiAlias,
+    oversampling: Oversampling,
+    oversampling_quality: OversamplingQuality,
+    linked: bool,
+    mid_side: bool,
+    // [0] - основной набор, [1] - правые каналы (или Side) в несвязанном режиме
+    sets: [SmoothedSet; 2],
+    spread: Smoother,
+    table_position: Smoother,
+    morph: Smoother,
+    table_morph: Smoother,
+}
+
+impl WaveFolderEngine {
+    // Начальная таблица - пила, как в плагине
+    pub fn new(num_channels: usize, sample_rate: f32) -> Self {
+        let samples = (0..2048)
+            .map(|s| s as f32 / 1024.0 - 1.0)
+            .collect::<Vec<_>>();
+        let table = Arc::new(Wavetable::new(samples, 0));
+        let mut engine = Self {
+            folder: Folder::new(num_channels),
+            num_channels,
+            previous_table: table.clone(),
+            table,
+            sample_rate,
+            smoothing: SmoothingSettings::default(),
+            crossfade_ms: DEFAULT_CROSSFADE_MS,
+            waveform: Waveform::Triangle,
+            next_waveform: Waveform::Triangle,
+            fade_waveform: Waveform::Triangle,
+            fade_from_previous: false,
+            fade: Smoother::new(1.0),
+            interpolation: Interpolation::Linear,
+            band_limit: false,
+            anti_alias: AntiAlias::Off,
+            oversampling: Oversampling::X1,
+            oversampling_quality: OversamplingQuality::Medium,
+            linked: true,
+            mid_side: false,
+            sets: [SmoothedSet::new(), SmoothedSet::new()],
+            spread: Smoother::new(0.0),
+            table_position: Smoother::new(0.0),
+            morph: Smoother::new(0.0),
+            table_morph: Smoother::new(0.0),
+        };
+        engine.update_smoothers();
+        engine
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_smoothers();
+    }
+
+    // Одно время сглаживания для всех параметров, стили не меняются
+    pub fn set_smoothing_time(&mut self, ms: f32) {
+        let mut smoothing = self.smoothing;
+        for entry in smoothing.entries_mut() {
+            entry.time_ms = ms.max(0.0);
+        }
+        self.set_smoothing(smoothing);
+    }
+
+    pub fn set_smoothing(&mut self, smoothing: SmoothingSettings) {
+        if smoothing != self.smoothing {
+            self.smoothing = smoothing;
+            self.update_smoothers();
+        }
+    }
+
+    // 0 - переключение без кроссфейда
+    pub fn set_crossfade_time(&mut self, ms: f32) {
+        self.crossfade_ms = ms.max(0.0);
+    }
+
+    // Drive в дБ
+    pub fn set_drive(&mut self, db: f32) {
+        self.sets[0].drive.set_target(db);
+    }
+
+    // Phase в градусах
+    pub fn set_phase(&mut self, degrees: f32) {
+        self.sets[0].phase.set_target(degrees);
+    }
+
+    pub fn set_dry_wet(&mut self, mix: f32) {
+        self.sets[0].dry_wet.set_target(mix);
+    }
+
+    // Второй набор: правые каналы без связки, в Mid/Side всегда канал S
+    pub fn set_second_drive(&mut self, db: f32) {
+        self.sets[1].drive.set_target(db);
+    }
+
+    pub fn set_second_phase(&mut self, degrees: f32) {
+        self.sets[1].phase.set_target(degrees);
+    }
+
+    pub fn set_second_dry_wet(&mut self, mix: f32) {
+        self.sets[1].dry_wet.set_target(mix);
+    }
+
+    // Разнос фазы между каналами пары в градусах
+    pub fn set_spread(&mut self, degrees: f32) {
+        self.spread.set_target(degrees);
+    }
+
+    pub fn set_table_position(&mut self, position: f32) {
+        self.table_position.set_target(position);
+    }
+
+    // 0..=4: синус, треугольник, пила, меандр, своя таблица
+    pub fn set_morph(&mut self, morph: f32) {
+        self.morph.set_target(morph);
+    }
+
+    pub fn set_table_morph(&mut self, morph: f32) {
+        self.table_morph.set_target(morph);
+    }
+
+    // Во время кроссфейда новая форма ждет его окончания
+    pub fn set_waveform(&mut self, waveform: Waveform) {
+        self.next_waveform = waveform;
+        self.switch_waveform();
+    }
+
+    // ADAA в любом режиме интегрирует линейную интерполяцию
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+    }
+
+    // Мип-уровни действуют только без ADAA
+    pub fn set_band_limit(&mut self, band_limit: bool) {
+        self.band_limit = band_limit;
+    }
+
+    pub fn set_anti_alias(&mut self, anti_alias: AntiAlias) {
+        self.anti_alias = anti_alias;
+    }
+
+    pub fn set_oversampling(&mut self, oversampling: Oversampling) {
+        self.oversampling = oversampling;
+    }
+
+    pub fn set_oversampling_quality(&mut self, quality: OversamplingQuality) {
+        self.oversampling_quality = quality;
+    }
+
+    pub fn set_linked(&mut self, linked: bool) {
+        self.linked = linked;
+    }
+
+    pub fn set_mid_side(&mut self, mid_side: bool) {
+        self.mid_side = mid_side;
+    }
+
+    // Выделяет память, не для аудиопотока
+    pub fn load_table(&mut self, samples: Vec<f32>) {
+        self.load_wavetable(Wavetable::new(samples, 0));
+    }
+
+    pub fn load_wavetable(&mut self, table: Wavetable) {
+        self.table = Arc::new(table);
+        self.previous_table = self.table.clone();
+    }
+
+    pub fn table(&self) -> &Wavetable {
+        &self.table
+    }
+
+    // Без ожиданий и выделений памяти; вытесненная таблица освобождается вне аудиопотока.
+    // Во время кроссфейда новая таблица ждет в handoff
+    #[inline]
+    pub fn receive_table(&mut self, handoff: &TableHandoff) -> bool {
+        // Предыдущая таблица нужна до конца кроссфейда, новая подождет в handoff
+        if self.fade.is_ramping() {
+            return false;
+        }
+        // Новая таблица приходит на место предыдущей, а текущая становится предыдущей
+        if !handoff.exchange(&mut self.previous_table) {
+            return false;
+        }
+        std::mem::swap(&mut self.table, &mut self.previous_table);
+        if self.waveform.uses_table() {
+            self.start_fade(self.waveform, true);
+        }
+        true
+    }
+
+    pub fn latency(&self) -> u32 {
+        self.settings().latency()
+    }
+
+    // Сбрасывает историю фильтров и ADAA, сглаживатели встают на цели
+    pub fn reset(&mut self) {
+        self.folder.reset();
+        for set in self.sets.iter_mut() {
+            set.smoothers().into_iter().for_each(Smoother::snap);
+        }
+        self.spread.snap();
+        self.table_position.snap();
+        self.morph.snap();
+        self.table_morph.snap();
+        self.fade.snap();
+        self.waveform = self.next_waveform;
+    }
+
+    pub fn process_block(&mut self, samples: &mut [f32]) {
+        self.process(&mut [samples]);
+    }
+
+    // Все каналы одной длины
+    pub fn process(&mut self, channels: &mut [&mut [f32]]) {
+        let num_samples = channels.first().map_or(0, |c| c.len());
+        self.switch_waveform();
+        let settings = self.settings();
+        let [main_set, second_set] = &mut self.sets;
+        let spread = &mut self.spread;
+        let table_position = &mut self.table_position;
+        let morph = &mut self.morph;
+        let table_morph = &mut self.table_morph;
+        let fade = &mut self.fade;
+
+        self.folder.process(
+            channels,
+            num_samples,
+            &settings,
+            &self.table,
+            if self.fade_from_previous {
+                &self.previous_table
+            } else {
+                &self.table
+            },
+            |[main_block, second_block], shared_block, block_len| {
+                // Второй набор продвигаем всегда, чтобы при расцеплении не было скачка
+                main_block.fill(
+                    &mut main_set.drive,
+                    &mut main_set.phase,
+                    &mut main_set.dry_wet,
+                    block_len,
+                );
+                second_block.fill(
+                    &mut second_set.drive,
+                    &mut second_set.phase,
+                    &mut second_set.dry_wet,
+                    block_len,
+                );
+                shared_block.fill(spread, table_position, morph, table_morph, fade, block_len);
+            },
+        );
+    }
+
+    fn settings(&self) -> FoldSettings {
+        FoldSettings {
+            waveform: self.waveform,
+            fade_waveform: self.fade_waveform,
+            interpolation: self.interpolation,
+            band_limit: self.band_limit,
+            anti_alias: self.anti_alias,
+            os_stages: self.oversampling.stages(),
+            os_quality: self.oversampling_quality.index(),
+            linked: self.linked,
+            mid_side: self.mid_side,
+        }
+    }
+
+    fn switch_waveform(&mut self) {
+        // Переключение посреди кроссфейда дало бы скачок: старая форма пропала бы сразу
+        if self.next_waveform != self.waveform && !self.fade.is_ramping() {
+            self.start_fade(self.waveform, false);
+            self.waveform = self.next_waveform;
+        }
+    }
+
+    fn start_fade(&mut self, from: Waveform, from_previous: bool) {
+        let length = (self.crossfade_ms / 1000.0 * self.sample_rate).round() as u32;
+        self.fade_waveform = from;
+        // Переприсвоить previous_table здесь нельзя: освобождение таблицы в аудиопотоке
+        self.fade_from_previous = from_previous;
+        self.fade = Smoother::new(0.0);
+        self.fade.set_length(length);
+        self.fade.set_target(1.0);
+        self.folder.start_fade();
+    }
+
+    fn update_smoothers(&mut self) {
+        let smoothing = self.smoothing;
+        let sample_rate = self.sample_rate;
+        for set in self.sets.iter_mut() {
+            // Drive сглаживается в дБ, в коэффициент он переводится уже после сглаживания
+            set.drive.set_smoothing(smoothing.drive, sample_rate);
+            set.phase.set_smoothing(smoothing.phase, sample_rate);
+            set.dry_wet.set_smoothing(smoothing.dry_wet, sample_rate);
+        }
+        self.spread.set_smoothing(smoothing.spread, sample_rate);
+        self.table_position
+            .set_smoothing(smoothing.table_position, sample_rate);
+        self.morph.set_smoothing(smoothing.shape, sample_rate);
+        self.table_morph
+            .set_smoothing(smoothing.table_morph, sample_rate);
+    }
+}
@@ -0,0 +1,208 @@
+// Интерполяция пользовательской таблицы. Все режимы работают на стеке, без аллокаций в аудиопотоке.
+use std::f32::consts::PI;
+
+use crate::engine::Interpolation;
+use crate::utils;
+
+// Отсчетов ядра sinc с каждой стороны
+const SINC_HALF_WIDTH: isize = 8;
+
+// Сетка как в utils::lookup_custom: первый и последний отсчет - на концах периода
+#[inline]
+pub fn lookup(table: &[f32], x: f32, interpolation: Interpolation) -> f32 {
+    if !x.is_finite() || table.len() < 2 {
+        return 0.0;
+    }
+
+    let mut normalized_x = (x % 4.0) / 4.0;
+    if normalized_x < 0.0 {
+        normalized_x += 1.0;
+    }
+    let index_f = normalized_x * (table.len() - 1) as f32;
+    let index = index_f.floor();
+    let t = index_f - index;
+    let k = index as isize;
+    let at = |offset: isize| sample(table, k + offset);
+
+    match interpolation {
+        Interpolation::Nearest => at(if t < 0.5 { 0 } else { 1 }),
+        // Линейная остается прежней функцией, чтобы звук по умолчанию не изменился
+        Interpolation::Linear => utils::lookup_custom(table, x),
+        Interpolation::Hermite => hermite([at(-1), at(0), at(1), at(2)], t),
+        Interpolation::CatmullRom => catmull_rom([at(-1), at(0), at(1), at(2)], t),
+        Interpolation::Lagrange4 => lagrange([at(-1), at(0), at(1), at(2)], -1, t),
+        Interpolation::Lagrange6 => lagrange([at(-2), at(-1), at(0), at(1), at(2), at(3)], -2, t),
+        Interpolation::Sinc => windowed_sinc(table, k, t),
+    }
+}
+
+// Период len - 1: последний отсчет совпадает с первым отсчетом следующего периода
+#[inline]
+fn sample(table: &[f32], index: isize) -> f32 {
+    let len = table.len() as isize;
+    if (0..len).contains(&index) {
+        table[index as usize]
+    } else {
+        table[index.rem_euclid(len - 1) as usize]
+    }
+}
+
+// Эрмит с касательными Fritsch-Butland: не выходит за монотонные отсчеты
+#[inline]
+fn hermite([y_prev, y0, y1, y_next]: [f32; 4], t: f32) -> f32 {
+    let tangent = |a: f32, b: f32| {
+        if a * b <= 0.0 {
+            0.0
+        } else {
+            2.0 * a * b / (a + b)
+        }
+    };
+    let m0 = tangent(y0 - y_prev, y1 - y0);
+    let m1 = tangent(y1 - y0, y_next - y1);
+
+    let t2 = t * t;
+    let t3 = t2 * t;
+    y0 * (2.0 * t3 - 3.0 * t2 + 1.0)
+        + m0 * (t3 - 2.0 * t2 + t)
+        + y1 * (3.0 * t2 - 2.0 * t3)
+        + m1 * (t3 - t2)
+}
+
+#[inline]
+fn catmull_rom([y_prev, y0, y1, y_next]: [f32; 4], t: f32) -> f32 {
+    let c1 = 0.5 * (y1 - y_prev);
+    let c2 = y_prev - 2.5 * y0 + 2.0 * y1 - 0.5 * y_next;
+    let c3 = 0.5 * (y_next - y_prev) + 1.5 * (y0 - y1);
+    ((c3 * t + c2) * t + c1) * t + y0
+}
+
+// Отсчеты first..first + N
+#[inline]
+fn lagrange<const N: usize>(points: [f32; N], first: isize, t: f32) -> f32 {
+    let mut sum = 0.0;
+    for (i, point) in points.iter().enumerate() {
+        let xi = (first + i as isize) as f32;
+        let mut weight = 1.0;
+        for j in 0..N {
+            if j != i {
+                let xj = (first + j as isize) as f32;
+                weight *= (t - xj) / (xi - xj);
+            }
+        }
+        sum += point * weight;
+    }
+    sum
+}
+
+// Окно Блэкмана
+#[inline]
+fn windowed_sinc(table: &[f32], k: isize, t: f32) -> f32 {
+    if t == 0.0 {
+        return sample(table, k);
+    }
+
+    // sin(pi * (t - j)) = (-1)^j * sin(pi * t), так что синус считаем один раз
+    let sin_pi_t = (PI * t).sin();
+    let mut sum = 0.0;
+    let mut weight_sum = 0.0;
+    for j in 1 - SINC_HALF_WIDTH..=SINC_HALF_WIDTH {
+        let distance = t - j as f32;
+        let sign = if j % 2 == 0 { 1.0 } else { -1.0 };
+        let sinc = sign * sin_pi_t / (PI * distance);
+        let u = distance / SINC_HALF_WIDTH as f32;
+        let window = 0.42 + 0.5 * (PI * u).cos() + 0.08 * (2.0 * PI * u).cos();
+        let weight = sinc * window;
+        sum += sample(table, k + j) * weight;
+        weight_sum += weight;
+    }
+    // Нормируем на сумму весов, чтобы постоянная таблица воспроизводилась точно
+    sum / weight_sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const MODES: [Interpolation; 7] = [
+        Interpolation::Nearest,
+        Interpolation::Linear,
+        Interpolation::Hermite,
+        Interpolation::CatmullRom,
+        Interpolation::Lagrange4,
+        Interpolation::Lagrange6,
+        Interpolation::Sinc,
+    ];
+
+    #[test]
+    fn short_tables_and_non_finite_input_are_silent() {
+        for mode in MODES {
+            assert_eq!(lookup(&[], 1.0, mode), 0.0);
+            assert_eq!(lookup(&[0.7], 1.0, mode), 0.0);
+            assert_eq!(lookup(&[0.5, -0.5, 1.0], f32::NAN, mode), 0.0);
+            assert_eq!(lookup(&[0.5, -0.5, 1.0], f32::INFINITY, mode), 0.0);
+        }
+    }
+
+    #[test]
+    fn constant_table_stays_constant() {
+        let table = [0.3; 16];
+        for mode in MODES {
+            for i in 0..64 {
+                let y = lookup(&table, i as f32 * 0.13, mode);
+                assert!((y - 0.3).abs() < 1.0e-5, "{:?}: {}", mode, y);
+            }
+        }
+    }
+
+    #[test]
+    fn hermite_does_not_overshoot_a_step() {
+        let table = [0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+        for i in 0..400 {
+            let y = lookup(&table, i as f32 * 0.01, Interpolation::Hermite);
+            assert!((0.0..=1.0).contains(&y), "{}", y);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn every_mode_passes_through_the_samples(
+            table in prop::collection::vec(-1.0f32..=1.0, 2..64),
+            index in 0usize..64,
+        ) {
+            let index = index % (table.len() - 1);
+            let x = index as f32 * 4.0 / (table.len() - 1) as f32;
+            // x попадает в отсчет с точностью до округления, отсюда допуск
+            for mode in MODES {
+                let y = lookup(&table, x, mode);
+                prop_assert!((y - table[index]).abs() < 1.0e-3, "{:?}: {}", mode, y);
+            }
+        }
+
+        #[test]
+        fn every_mode_has_period_4(
+            table in prop::collection::vec(-1.0f32..=1.0, 2..64),
+            x in -20.0f32..20.0,
+        ) {
+            let phase = x.rem_euclid(4.0);
+            prop_assume!(phase > 1.0e-3 && phase < 4.0 - 1.0e-3);
+            // Ближайший отсчет на середине между отсчетами может переключиться от округления
+            for mode in MODES.into_iter().filter(|&m| m != Interpolation::Nearest) {
+                let a = lookup(&table, x, mode);
+                let b = lookup(&table, x + 4.0, mode);
+                prop_assert!((a - b).abs() < 1.0e-3, "{:?}: {} vs {}", mode, a, b);
+            }
+        }
+
+        #[test]
+        fn linear_matches_lookup_custom(
+            table in prop::collection::vec(-1.0f32..=1.0, 2..64),
+            x in any::<f32>(),
+        ) {
+            prop_assert_eq!(
+                lookup(&table, x, Interpolation::Linear).to_bits(),
+                utils::lookup_custom(&table, x).to_bits()
+            );
+        }
+    }
+}
@@ -4,22 +4,53 @@ use nih_plug::prelude::*; // Импортируем все необходимы
 use nih_plug::wrapper::vst3::subcategories::Vst3SubCategory; // Импортируем Vst3SubCategory из правильного пути
 use nih_plug_egui::{EguiState, create_egui_editor, egui, widgets};
 use parking_lot::RwLock;
+use std::path::Path;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
+use crate::engine::SmoothingStyle;
+use crate::wav_reader::{LoadError, Normalization, TableChannels};
+
+mod adaa;
+mod decoder;
+mod dsp;
+pub mod engine;
+mod interpolation;
+mod mipmap;
+mod oversampling;
+pub mod render;
+mod smoothing;
+mod table_handoff;
+mod table_io;
+mod table_morph;
+mod table_state;
 mod utils;
 mod wav_reader;
+mod wavetable;
 mod wf_params;
+mod wt_reader;
 mod zero_crossing_detector;
 
-struct WF {
+pub struct WF {
     params: Arc<wf_params::WFParams>,
     last_open_file_state: bool,
-    // Используем RwLock вместо ArcSwap для стабильности в Ardour
-    custom_waveform: Arc<RwLock<Arc<Vec<f32>>>>,
+    // Таблица хранит и кадры, и их интегралы для ADAA.
+    // Аудиопоток забирает ее атомарным обменом, без блокировок и освобождения памяти.
+    custom_waveform: Arc<engine::TableHandoff>,
     editor_state: Arc<EguiState>,
-    zero_crossing_points: Arc<RwLock<Vec<f32>>>,
+    // Точки перехода через ноль для каждого кадра таблицы
+    zero_crossing_points: Arc<RwLock<Vec<Vec<f32>>>>,
+    // Число каналов в загруженном файле, чтобы редактор предлагал только существующие
+    table_file_channels: Arc<AtomicUsize>,
+    // Держит ли слот A таблицу файла waveform_path, загруженную или восстановленную
+    table_loaded: Arc<AtomicBool>,
+    // Ошибки последней загрузки слотов A и B, редактор показывает их баннерами
+    load_error: Arc<RwLock<Option<LoadError>>>,
+    slot_b_error: Arc<RwLock<Option<LoadError>>>,
     zc_input_buffer: Arc<RwLock<String>>,
     trunked_val: Arc<RwLock<usize>>,
+    engine: engine::WaveFolderEngine,
+    latency: u32,
 }
 
 impl Default for WF {
@@ -31,18 +62,79 @@ impl Default for WF {
         Self {
             params: Arc::new(wf_params::WFParams::default()),
             last_open_file_state: false,
-            custom_waveform: Arc::new(RwLock::new(Arc::new(default_table))),
-            editor_state: EguiState::from_size(740, 475),
-            zero_crossing_points: Arc::new(RwLock::new(vec![0.5])),
+            custom_waveform: Arc::new(engine::TableHandoff::new(engine::Wavetable::new(
+                default_table,
+                0,
+            ))),
+            editor_state: EguiState::from_size(740, 760),
+            zero_crossing_points: Arc::new(RwLock::new(vec![vec![0.5]])),
+            table_file_channels: Arc::new(AtomicUsize::new(1)),
+            table_loaded: Arc::new(AtomicBool::new(false)),
+            load_error: Arc::new(RwLock::new(None)),
+            slot_b_error: Arc::new(RwLock::new(None)),
             zc_input_buffer: Arc::new(RwLock::new(String::new())),
             trunked_val: Arc::new(RwLock::new(0)),
+            engine: engine::WaveFolderEngine::new(1, 44100.0),
+            latency: 0,
         }
     }
 }
 
-enum WFBackgroundTask {
+pub enum WFBackgroundTask {
     LoadFile,
     LoadFileNoDialog,
+    // Таблица из состояния сессии, а если ее там нет - из файла по сохраненному пути
+    RestoreTable,
+    // Сохранение текущей таблицы в состояние или удаление ее оттуда
+    UpdateEmbeddedTable,
+    // Ручной выбор пропавшего файла
+    LocateFile,
+    // Выбор папки библиотеки и поиск пропавшего файла в ней
+    SetLibraryRoot,
+    // Сохранение текущей таблицы в текст или сырые f32
+    ExportFile,
+    // Таблица слота B для Table Morph
+    LoadSlotB,
+    ClearSlotB,
+    // Пересборка слота B после переключения выравнивания по нулям
+    AlignSlotB,
+}
+
+impl WF {
+    // Передаем текущие значения параметров в движок, сглаживает он сам
+    fn update_engine(&mut self) {
+        let params = &self.params;
+        let engine = &mut self.engine;
+        engine.set_drive(params.gain.value());
+        engine.set_phase(params.phase.value());
+        engine.set_dry_wet(params.dw.value());
+        engine.set_second_drive(params.gain_r.value());
+        engine.set_second_phase(params.phase_r.value());
+        engine.set_second_dry_wet(params.dw_r.value());
+        engine.set_spread(params.spread.value());
+        engine.set_table_position(params.table_position.value());
+        engine.set_table_morph(params.table_morph.value());
+        engine.set_crossfade_time(params.crossfade.value());
+        engine.set_waveform(engine::Waveform::from_index(params.waveform.value()));
+        engine.set_morph(params.shape.value());
+        engine.set_interpolation(engine::Interpolation::from_index(
+            params.interpolation.value(),
+        ));
+        engine.set_band_limit(params.band_limit.value());
+        engine.set_anti_alias(engine::AntiAlias::from_index(params.anti_alias.value()));
+        engine.set_oversampling(engine::Oversampling::from_index(
+            params.oversampling.value(),
+        ));
+        engine.set_oversampling_quality(engine::OversamplingQuality::from_index(
+            params.oversampling_quality.value(),
+        ));
+        engine.set_linked(params.link.value());
+        engine.set_mid_side(params.mid_side.value());
+        // Настройки пишет редактор; если он как раз их меняет, возьмем их в следующем блоке
+        if let Some(smoothing) = params.smoothing.try_read() {
+            engine.set_smoothing(*smoothing);
+        }
+    }
 }
 
 impl Plugin for WF {
@@ -55,13 +147,44 @@ impl Plugin for WF {
     const EMAIL: &'static str = "None";
     const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
-        main_input_channels: NonZeroU32::new(1),
-        main_output_channels: NonZeroU32::new(1),
-        aux_input_ports: &[],
-        aux_output_ports: &[],
-        names: PortNames::const_default(),
-    }];
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+        // Многоканальные раскладки: каналы идут парами левый/правый
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(4),
+            main_output_channels: NonZeroU32::new(4),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(6),
+            main_output_channels: NonZeroU32::new(6),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(8),
+            main_output_channels: NonZeroU32::new(8),
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            names: PortNames::const_default(),
+        },
+    ];
 
     const MIDI_INPUT: MidiConfig = MidiConfig::None;
     const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
@@ -74,10 +197,20 @@ impl Plugin for WF {
 
     fn initialize(
         &mut self,
-        _audio_io_layout: &AudioIOLayout,
-        _buffer_config: &BufferConfig,
+        audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
         context: &mut impl InitContext<Self>,
     ) -> bool {
+        let num_channels = audio_io_layout
+            .main_output_channels
+            .map(NonZeroU32::get)
+            .unwrap_or(0) as usize;
+        self.engine = engine::WaveFolderEngine::new(num_channels, buffer_config.sample_rate);
+        self.update_engine();
+        self.engine.reset();
+        self.latency = self.engine.latency();
+        context.set_latency_samples(self.latency);
+
         self.zc_input_buffer = Arc::new(RwLock::new(String::new()));
         self.trunked_val = Arc::new(RwLock::new(0));
         self.last_open_file_state = false;
@@ -85,48 +218,51 @@ impl Plugin for WF {
         let default_table = (0..2048)
             .map(|s| s as f32 / 1024.0 - 1.0)
             .collect::<Vec<_>>();
-        *self.custom_waveform.write() = Arc::new(default_table);
-        *self.zero_crossing_points.write() = vec![0.5];
+        self.custom_waveform
+            .publish_slot_b(None, *self.params.align_crossings.read());
+        self.custom_waveform
+            .publish(engine::Wavetable::new(default_table, 0));
+        *self.zero_crossing_points.write() = vec![vec![0.5]];
+        self.table_file_channels.store(1, Ordering::Relaxed);
+        self.table_loaded.store(false, Ordering::Relaxed);
+        *self.load_error.write() = None;
+        *self.slot_b_error.write() = None;
         let path = self.params.waveform_path.read().clone();
-        if !path.is_empty() {
-            context.execute(WFBackgroundTask::LoadFileNoDialog);
+        let slot_b_path = self.params.slot_b_path.read().clone();
+        if !path.is_empty()
+            || !slot_b_path.is_empty()
+            || self.params.embedded_table.read().is_some()
+            || self.params.embedded_slot_b.read().is_some()
+        {
+            context.execute(WFBackgroundTask::RestoreTable);
         }
 
         true
     }
 
-    fn reset(&mut self) {}
+    fn reset(&mut self) {
+        self.update_engine();
+        self.engine.reset();
+    }
 
     fn process(
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        let table_lock = self.custom_waveform.read();
-        let custom_table = &**table_lock; // &[f32]
-
-        for channel_samples in buffer.as_slice() {
-            for sample in channel_samples.iter_mut() {
-                let gain = utils::db_to_gain(self.params.gain.smoothed.next());
-                let phase_offset = self.params.phase.smoothed.next() / 90.0;
-                let dry_wet = self.params.dw.smoothed.next();
-
-                let input_folded = *sample * gain + phase_offset;
-
-                let wet = match self.params.waveform.value() {
-                    0 => utils::sine(input_folded),
-                    1 => utils::triangle(input_folded),
-                    2 => utils::saw(input_folded),
-                    3 => utils::meander(input_folded),
-                    4 => utils::lookup_custom(custom_table, input_folded),
-                    _ => utils::sine(input_folded),
-                };
-
-                *sample = utils::xfader(*sample, wet, dry_wet);
-            }
+        // Новая таблица, если загрузчик ее опубликовал; старую освободит поток загрузчика или редактора
+        self.engine.receive_table(&self.custom_waveform);
+
+        self.update_engine();
+        let latency = self.engine.latency();
+        if latency != self.latency {
+            self.latency = latency;
+            context.set_latency_samples(latency);
         }
 
+        self.engine.process(buffer.as_slice());
+
         ProcessStatus::Normal
     }
 
@@ -134,33 +270,205 @@ impl Plugin for WF {
         let params = self.params.clone();
         let custom_waveform = self.custom_waveform.clone();
         let zero_crossing_points = self.zero_crossing_points.clone();
+        let table_file_channels = self.table_file_channels.clone();
+        let table_loaded = self.table_loaded.clone();
+        let load_error = self.load_error.clone();
+        let slot_b_error = self.slot_b_error.clone();
         Box::new(move |task| {
+            // Копия таблиц в состоянии сессии, если она включена. Пока файл слота не
+            // загрузился, остается прежняя копия, а не таблица по умолчанию
+            let update_embedded_table = || {
+                let embed_table = *params.embed_table.read();
+                if !embed_table || params.waveform_path.read().is_empty() {
+                    *params.embedded_table.write() = None;
+                } else if table_loaded.load(Ordering::Relaxed) {
+                    *params.embedded_table.write() = table_state::EmbeddedTable::capture(
+                        &custom_waveform.slot_a(),
+                        table_file_channels.load(Ordering::Relaxed),
+                    );
+                }
+                // Слот B хранится до выравнивания, оно повторяется при восстановлении
+                if !embed_table || params.slot_b_path.read().is_empty() {
+                    *params.embedded_slot_b.write() = None;
+                } else if let Some(slot_b) = custom_waveform.slot_b() {
+                    let channels = if slot_b.is_stereo() { 2 } else { 1 };
+                    *params.embedded_slot_b.write() =
+                        table_state::EmbeddedTable::capture(&slot_b, channels);
+                }
+            };
+            let load_file = |path_str: &str| {
+                // Здесь вызываем загрузку (внутри будет lock.write())
+                match wav_reader::process_wav_from_path(
+                    path_str,
+                    params.load_options(),
+                    &custom_waveform,
+                    &zero_crossing_points,
+                ) {
+                    Ok(channels) => {
+                        table_file_channels.store(channels, Ordering::Relaxed);
+                        table_loaded.store(true, Ordering::Relaxed);
+                        *load_error.write() = None;
+                        true
+                    }
+                    Err(err) => {
+                        *load_error.write() = Some(err);
+                        false
+                    }
+                }
+            };
+            let load_slot_b = |path_str: &str| match wav_reader::process_slot_b_from_path(
+                path_str,
+                params.load_options(),
+                *params.align_crossings.read(),
+                &custom_waveform,
+            ) {
+                Ok(()) => {
+                    *slot_b_error.write() = None;
+                    true
+                }
+                Err(err) => {
+                    *slot_b_error.write() = Some(err);
+                    false
+                }
+            };
+            let load_and_remember = |path_str: String| {
+                if load_file(&path_str) {
+                    *params.waveform_path.write() = path_str;
+                    update_embedded_table();
+                }
+            };
+            // Пропавший файл ищем по имени в папке библиотеки и рядом с его прежним местом
+            let locate_missing = || {
+                let path_str = params.waveform_path.read().clone();
+                let missing = Path::new(&path_str);
+                let roots = wav_reader::locate_roots(missing, &params.library_root.read());
+                if let Some(found) = wav_reader::locate_file(missing, &roots) {
+                    load_and_remember(found.to_string_lossy().into_owned());
+                }
+            };
+
             match task {
                 WFBackgroundTask::LoadFileNoDialog => {
+                    // Настройки загрузки общие для обоих слотов
                     let path_str = params.waveform_path.read().clone();
-                    if !path_str.is_empty() {
-                        // Здесь вызываем загрузку (внутри будет lock.write())
-                        wav_reader::process_wav_from_path(
-                            &path_str,
-                            &custom_waveform,
-                            &zero_crossing_points,
-                        );
+                    let slot_b_path = params.slot_b_path.read().clone();
+                    let loaded = !path_str.is_empty() && load_file(&path_str);
+                    let loaded_b = !slot_b_path.is_empty() && load_slot_b(&slot_b_path);
+                    if loaded || loaded_b {
+                        update_embedded_table();
                     }
                 }
+                WFBackgroundTask::RestoreTable => {
+                    // Оба слота читаются до загрузки: update_embedded_table перезаписывает обе копии
+                    let embedded_table = params.embedded_table.read().clone();
+                    let embedded_slot_b = params.embedded_slot_b.read().clone();
+                    let mut loaded_from_file = false;
+
+                    match embedded_table {
+                        Some(embedded_table) => {
+                            embedded_table.restore(&custom_waveform, &zero_crossing_points);
+                            table_file_channels
+                                .store(embedded_table.file_channels(), Ordering::Relaxed);
+                            table_loaded.store(true, Ordering::Relaxed);
+                        }
+                        None => {
+                            let path_str = params.waveform_path.read().clone();
+                            if !path_str.is_empty() {
+                                if load_file(&path_str) {
+                                    loaded_from_file = true;
+                                } else if matches!(*load_error.read(), Some(LoadError::NotFound(_)))
+                                {
+                                    locate_missing();
+                                }
+                            }
+                        }
+                    }
+
+                    match embedded_slot_b {
+                        Some(embedded_slot_b) => custom_waveform.publish_slot_b(
+                            Some(Arc::new(embedded_slot_b.to_wavetable())),
+                            *params.align_crossings.read(),
+                        ),
+                        None => {
+                            let slot_b_path = params.slot_b_path.read().clone();
+                            if !slot_b_path.is_empty() && load_slot_b(&slot_b_path) {
+                                loaded_from_file = true;
+                            }
+                        }
+                    }
+
+                    if loaded_from_file {
+                        update_embedded_table();
+                    }
+                }
+                WFBackgroundTask::UpdateEmbeddedTable => update_embedded_table(),
                 WFBackgroundTask::LoadFile => {
+                    // При ошибке остается прежняя таблица и прежний путь
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Audio", wav_reader::SUPPORTED_EXTENSIONS)
+                        .pick_file()
+                    {
+                        load_and_remember(path.to_string_lossy().into_owned());
+                    }
+                }
+                WFBackgroundTask::LocateFile => {
+                    let missing = params.waveform_path.read().clone();
+                    let library_root = params.library_root.read().clone();
+                    let mut dialog = rfd::FileDialog::new()
+                        .add_filter("Audio", wav_reader::SUPPORTED_EXTENSIONS);
+                    if !library_root.is_empty() {
+                        dialog = dialog.set_directory(&library_root);
+                    }
+                    if let Some(file_name) = Path::new(&missing).file_name() {
+                        dialog = dialog.set_file_name(file_name.to_string_lossy());
+                    }
+                    if let Some(path) = dialog.pick_file() {
+                        load_and_remember(path.to_string_lossy().into_owned());
+                    }
+                }
+                WFBackgroundTask::SetLibraryRoot => {
+                    if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                        *params.library_root.write() = folder.to_string_lossy().into_owned();
+                        if matches!(*load_error.read(), Some(LoadError::NotFound(_))) {
+                            locate_missing();
+                        }
+                    }
+                }
+                WFBackgroundTask::ExportFile => {
                     if let Some(path) = rfd::FileDialog::new()
-                        .add_filter("WAV", &["wav"])
+                        .add_filter("Text", &["csv", "txt"])
+                        .add_filter("Raw f32", &["f32", "raw"])
+                        .set_file_name("table.csv")
+                        .save_file()
+                    {
+                        let table = custom_waveform.slot_a();
+                        if let Err(err) = table_io::export_table(&path, &table) {
+                            nih_log!("Failed to export table to {}: {}", path.display(), err);
+                        }
+                    }
+                }
+                WFBackgroundTask::LoadSlotB => {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Audio", wav_reader::SUPPORTED_EXTENSIONS)
                         .pick_file()
                     {
                         let path_str = path.to_string_lossy().into_owned();
-                        wav_reader::process_wav_from_path(
-                            &path_str,
-                            &custom_waveform,
-                            &zero_crossing_points,
-                        );
-                        *params.waveform_path.write() = path_str;
+                        if load_slot_b(&path_str) {
+                            *params.slot_b_path.write() = path_str;
+                            update_embedded_table();
+                        }
                     }
                 }
+                WFBackgroundTask::ClearSlotB => {
+                    custom_waveform.publish_slot_b(None, *params.align_crossings.read());
+                    params.slot_b_path.write().clear();
+                    *slot_b_error.write() = None;
+                    update_embedded_table();
+                }
+                WFBackgroundTask::AlignSlotB => {
+                    custom_waveform
+                        .publish_slot_b(custom_waveform.slot_b(), *params.align_crossings.read());
+                }
             }
         })
     }
@@ -169,6 +477,9 @@ impl Plugin for WF {
         let params = self.params.clone();
         let waveform_arc = self.custom_waveform.clone();
         let zc_points_arc = self.zero_crossing_points.clone();
+        let table_file_channels = self.table_file_channels.clone();
+        let load_error_arc = self.load_error.clone();
+        let slot_b_error_arc = self.slot_b_error.clone();
         let string_buffer_arc = self.zc_input_buffer.clone();
         let trunked_value_arc = self.trunked_val.clone();
 
@@ -177,14 +488,17 @@ impl Plugin for WF {
         const ZERO_CROSSING_LINE_TEXT_COLOR: egui::Color32 = egui::Color32::from_rgb(127, 255, 127);
         const PHASE_LINE_TEXT_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 255, 128);
         const GRAPH_LINE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 0, 0);
+        const RIGHT_GRAPH_LINE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 160, 0);
+        const SLOT_B_GRAPH_LINE_COLOR: egui::Color32 = egui::Color32::from_rgb(0, 200, 255);
         const POSITIVE_COLOR: egui::Color32 = egui::Color32::from_rgb(0, 240, 0);
         const NEGATIVE_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 0, 0);
 
         create_egui_editor(
             self.editor_state.clone(),
-            (),
-            |_ctx, _data| {},
-            move |egui_ctx, setter, _data| {
+            // Открыто ли окно настроек сглаживания
+            false,
+            |_ctx, _show_smoothing| {},
+            move |egui_ctx, setter, show_smoothing| {
                 egui::CentralPanel::default().show(egui_ctx, |ui| {
                     ui.vertical_centered(|ui| {
                         ui.heading(
@@ -194,6 +508,46 @@ impl Plugin for WF {
                         );
                     });
 
+                    // --- БАННЕРЫ ОШИБОК ЗАГРУЗКИ ---
+                    for (error_arc, slot_a) in [(&load_error_arc, true), (&slot_b_error_arc, false)]
+                    {
+                        let load_error = error_arc.read().clone();
+                        let Some(err) = load_error else {
+                            continue;
+                        };
+                        ui.add_space(5.0);
+                        egui::Frame::new()
+                            .fill(NEGATIVE_COLOR.linear_multiply(0.2))
+                            .stroke(egui::Stroke::new(1.0, NEGATIVE_COLOR))
+                            .corner_radius(4.0)
+                            .inner_margin(6.0)
+                            .show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                    let message = if slot_a {
+                                        err.to_string()
+                                    } else {
+                                        format!("Slot B: {}", err)
+                                    };
+                                    ui.label(RichText::new(message).color(NEGATIVE_COLOR));
+                                    // Искать можно только файл слота A
+                                    if slot_a && matches!(err, LoadError::NotFound(_)) {
+                                        if ui.button("Locate file…").clicked() {
+                                            async_executor
+                                                .execute_background(WFBackgroundTask::LocateFile);
+                                        }
+                                        if ui.button("Library folder…").clicked() {
+                                            async_executor.execute_background(
+                                                WFBackgroundTask::SetLibraryRoot,
+                                            );
+                                        }
+                                    }
+                                    if ui.small_button("✕").clicked() {
+                                        *error_arc.write() = None;
+                                    }
+                                });
+                            });
+                    }
+
                     ui.add_space(15.0);
 
                     // --- 1. ВИЗУАЛИЗАЦИЯ ГРАФИКА ---
@@ -209,11 +563,23 @@ impl Plugin for WF {
 
                     // Читаем данные из RwLock
 
-                    let table_guard = waveform_arc.read(); // Блокируем один раз
-                    let samples = &**table_guard; // Разыменовываем до &[f32]
-                    let zc_points = zc_points_arc.read();
+                    // Заодно освобождаем таблицу, вытесненную из аудиопотока
+                    waveform_arc.collect_retired();
+                    let table_arc = waveform_arc.current();
+                    let table = &*table_arc;
+                    let position = params.table_position.value();
+                    let interpolation =
+                        engine::Interpolation::from_index(params.interpolation.value());
+                    // Нули показываем для кадра, ближайшего к текущей позиции
+                    let nearest_frame =
+                        (position * (table.frame_count() - 1) as f32).round() as usize;
+                    let zc_frames = zc_points_arc.read();
+                    let zc_points = zc_frames
+                        .get(nearest_frame)
+                        .map(Vec::as_slice)
+                        .unwrap_or(&[]);
                     let mut trunked_val = trunked_value_arc.write();
-                    if !samples.is_empty() {
+                    if !table.samples().is_empty() {
                         let mid_y = rect.center().y;
                         let height_scale = rect.height() * 0.4;
                         let width = rect.width();
@@ -247,7 +613,7 @@ impl Plugin for WF {
 
                         for i in 0..width as usize {
                             let t = (i as f32 / width) * 4.0;
-                            let sample = utils::lookup_custom(samples, t);
+                            let sample = table.lookup(position, t, interpolation);
 
                             let x = rect.left() + i as f32;
                             let y = mid_y - (sample * height_scale);
@@ -278,8 +644,30 @@ impl Plugin for WF {
                             }
                         }
 
-                        {
-                            let val = params.phase.value() / 360.0;
+                        // Метки фазы: одна общая или по одной на каждый канал пары
+                        let spread = params.spread.value();
+                        let linked = params.link.value() && !params.mid_side.value();
+                        let (first, second) = if params.mid_side.value() {
+                            ("M ", "S ")
+                        } else {
+                            ("L ", "R ")
+                        };
+                        let second_phase = if linked {
+                            params.phase.value()
+                        } else {
+                            params.phase_r.value()
+                        };
+                        let phase_markers = if linked && spread == 0.0 {
+                            vec![("", params.phase.value())]
+                        } else {
+                            vec![
+                                (first, params.phase.value() - spread / 2.0),
+                                (second, second_phase + spread / 2.0),
+                            ]
+                        };
+
+                        for (label, phase) in phase_markers {
+                            let val = phase.rem_euclid(360.0) / 360.0;
                             let x = rect.left() + val * rect.width();
                             // Рисуем вертикальную линию
                             painter.line_segment(
@@ -295,13 +683,48 @@ impl Plugin for WF {
                             painter.text(
                                 egui::pos2(x, rect.bottom() - 5.0),
                                 egui::Align2::CENTER_BOTTOM,
-                                format!("{}°", degrees),
+                                format!("{}{}°", label, degrees),
                                 egui::FontId::monospace(9.0),
                                 PHASE_LINE_TEXT_COLOR,
                             );
                         }
 
                         // --- 3. САМА ЛИНИЯ ГРАФИКА ---
+                        // Правый канал стереотаблицы рисуем под левым
+                        if table.is_stereo() {
+                            let right = table.channel(1);
+                            let right_points = (0..width as usize)
+                                .map(|i| {
+                                    let t = (i as f32 / width) * 4.0;
+                                    let sample = right.lookup(position, t, interpolation);
+                                    egui::pos2(
+                                        rect.left() + i as f32,
+                                        mid_y - sample * height_scale,
+                                    )
+                                })
+                                .collect::<Vec<_>>();
+                            painter.add(egui::Shape::line(
+                                right_points,
+                                egui::Stroke::new(1.5, RIGHT_GRAPH_LINE_COLOR),
+                            ));
+                        }
+                        // Таблица слота B, уже выровненная, если выравнивание включено
+                        if let Some(slot_b) = table.slot_b() {
+                            let slot_b_points = (0..width as usize)
+                                .map(|i| {
+                                    let t = (i as f32 / width) * 4.0;
+                                    let sample = slot_b.lookup(position, t, interpolation);
+                                    egui::pos2(
+                                        rect.left() + i as f32,
+                                        mid_y - sample * height_scale,
+                                    )
+                                })
+                                .collect::<Vec<_>>();
+                            painter.add(egui::Shape::line(
+                                slot_b_points,
+                                egui::Stroke::new(1.5, SLOT_B_GRAPH_LINE_COLOR),
+                            ));
+                        }
                         painter.add(egui::Shape::line(
                             points,
                             egui::Stroke::new(2.0, GRAPH_LINE_COLOR),
@@ -358,7 +781,8 @@ impl Plugin for WF {
                                     (1, "Triangle"),
                                     (2, "Saw"),
                                     (3, "Square"),
-                                    (4, "From WAV"),
+                                    (4, "From file"),
+                                    (5, "Morph"),
                                 ] {
                                     if ui.radio_value(&mut current_wave, val, label).changed() {
                                         setter.begin_set_parameter(&params.waveform);
@@ -367,16 +791,157 @@ impl Plugin for WF {
                                     }
                                 }
                             });
+                            ui.horizontal(|ui| {
+                                ui.label("Interpolation:");
+                                let mut current = params.interpolation.value();
+                                egui::ComboBox::from_id_salt("interpolation")
+                                    .selected_text(params.interpolation.to_string())
+                                    .show_ui(ui, |ui| {
+                                        for (val, label) in [
+                                            (0, "Nearest"),
+                                            (1, "Linear"),
+                                            (2, "Hermite"),
+                                            (3, "Catmull-Rom"),
+                                            (4, "Lagrange 4"),
+                                            (5, "Lagrange 6"),
+                                            (6, "Windowed sinc"),
+                                        ] {
+                                            if ui
+                                                .selectable_value(&mut current, val, label)
+                                                .changed()
+                                            {
+                                                setter.begin_set_parameter(&params.interpolation);
+                                                setter
+                                                    .set_parameter(&params.interpolation, current);
+                                                setter.end_set_parameter(&params.interpolation);
+                                            }
+                                        }
+                                    });
+
+                                let mut band_limit = params.band_limit.value();
+                                if ui.checkbox(&mut band_limit, "Band-limit").changed() {
+                                    setter.begin_set_parameter(&params.band_limit);
+                                    setter.set_parameter(&params.band_limit, band_limit);
+                                    setter.end_set_parameter(&params.band_limit);
+                                }
+                            });
+                            // Морф от синуса через треугольник, пилу и меандр к таблице
+                            if params.waveform.value() == 5 {
+                                ui.horizontal(|ui| {
+                                    ui.label("Shape:");
+                                    ui.add(widgets::ParamSlider::for_param(&params.shape, setter));
+                                });
+                            }
+                            ui.horizontal(|ui| {
+                                ui.label("Crossfade:");
+                                ui.add(widgets::ParamSlider::for_param(&params.crossfade, setter));
+                            });
                         });
 
                         // --- 3. КНОПКА ВЫБОРА ФАЙЛА ---
                         ui.horizontal(|ui| {
-                            if ui.button("Load WAV").clicked() {
+                            if ui.button("Load file").clicked() {
                                 // Используем переданный экзекутор для вызова диалога
                                 async_executor.execute_background(WFBackgroundTask::LoadFile);
                             }
+                            if ui.button("Export").clicked() {
+                                async_executor.execute_background(WFBackgroundTask::ExportFile);
+                            }
+                            // Без встраивания в сессии остается только путь к файлу
+                            let mut embed_table = *params.embed_table.read();
+                            if ui.checkbox(&mut embed_table, "Embed in session").changed() {
+                                *params.embed_table.write() = embed_table;
+                                async_executor
+                                    .execute_background(WFBackgroundTask::UpdateEmbeddedTable);
+                            }
+                            // Большая таблица хранится только путем
+                            if embed_table
+                                && !params.waveform_path.read().is_empty()
+                                && table.samples().len() > table_state::EMBED_MAX_SAMPLES
+                            {
+                                ui.label(
+                                    egui::RichText::new("too large, path only")
+                                        .italics()
+                                        .size(10.0),
+                                );
+                            }
                         });
 
+                        // Размер кадра: авто (чанк clm) или вручную; меняется - перечитываем файл
+                        let frame_size = *params.frame_size.read();
+                        egui::ComboBox::from_label("Frame size")
+                            .selected_text(frame_size_label(frame_size))
+                            .show_ui(ui, |ui| {
+                                for size in [0, 256, 512, 1024, 2048, 4096] {
+                                    if ui
+                                        .selectable_label(
+                                            size == frame_size,
+                                            frame_size_label(size),
+                                        )
+                                        .clicked()
+                                        && size != frame_size
+                                    {
+                                        *params.frame_size.write() = size;
+                                        async_executor
+                                            .execute_background(WFBackgroundTask::LoadFileNoDialog);
+                                    }
+                                }
+                            });
+
+                        let normalization = *params.normalization.read();
+                        egui::ComboBox::from_label("Normalize")
+                            .selected_text(normalization_label(normalization))
+                            .show_ui(ui, |ui| {
+                                for option in [
+                                    Normalization::None,
+                                    Normalization::Peak,
+                                    Normalization::Rms,
+                                    Normalization::DcPeak,
+                                    Normalization::SymmetricPeak,
+                                ] {
+                                    if ui
+                                        .selectable_label(
+                                            option == normalization,
+                                            normalization_label(option),
+                                        )
+                                        .clicked()
+                                        && option != normalization
+                                    {
+                                        *params.normalization.write() = option;
+                                        async_executor
+                                            .execute_background(WFBackgroundTask::LoadFileNoDialog);
+                                    }
+                                }
+                            });
+
+                        // Выбор каналов нужен только для многоканального файла
+                        let file_channels = table_file_channels.load(Ordering::Relaxed);
+                        if file_channels > 1 {
+                            let current = *params.table_channels.read();
+                            let options = [TableChannels::Mix, TableChannels::Stereo]
+                                .into_iter()
+                                .chain((0..file_channels).map(TableChannels::Channel));
+                            egui::ComboBox::from_label("Channels")
+                                .selected_text(table_channels_label(current))
+                                .show_ui(ui, |ui| {
+                                    for option in options {
+                                        if ui
+                                            .selectable_label(
+                                                option == current,
+                                                table_channels_label(option),
+                                            )
+                                            .clicked()
+                                            && option != current
+                                        {
+                                            *params.table_channels.write() = option;
+                                            async_executor.execute_background(
+                                                WFBackgroundTask::LoadFileNoDialog,
+                                            );
+                                        }
+                                    }
+                                });
+                        }
+
                         // Вывод текущего пути (если есть)
                         let path = params.waveform_path.read();
                         if !path.is_empty() {
@@ -388,33 +953,297 @@ impl Plugin for WF {
                         }
                     });
 
-                    ui.add_space(10.0);
-                    // Слайдеры
-                    let slider_size = egui::vec2(ui.available_width(), 20.0);
+                    // Позиция имеет смысл только для таблицы из нескольких кадров
+                    let frame_count = table.frame_count();
+                    if frame_count > 1 {
+                        ui.add_space(5.0);
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new(format!(
+                                "Table Position ({} frames)",
+                                frame_count
+                            )));
+                            // Выбор кадра ставит позицию ровно на него
+                            ui.label("Frame:");
+                            let mut frame = nearest_frame + 1;
+                            if ui
+                                .add(egui::DragValue::new(&mut frame).range(1..=frame_count))
+                                .changed()
+                            {
+                                let position = (frame - 1) as f32 / (frame_count - 1) as f32;
+                                setter.begin_set_parameter(&params.table_position);
+                                setter.set_parameter(&params.table_position, position);
+                                setter.end_set_parameter(&params.table_position);
+                            }
+                        });
+                        ui.add_sized(
+                            egui::vec2(ui.available_width(), 20.0),
+                            widgets::ParamSlider::for_param(&params.table_position, setter),
+                        );
+                    }
 
-                    ui.label(egui::RichText::new("Dry/Wet"));
-                    ui.add_sized(
-                        slider_size,
-                        widgets::ParamSlider::for_param(&params.dw, setter),
-                    );
+                    // --- СЛОТ B ДЛЯ TABLE MORPH ---
                     ui.add_space(5.0);
-                    ui.label(egui::RichText::new("Drive"));
+                    ui.horizontal(|ui| {
+                        ui.label("Slot B:");
+                        if ui.button("Load B").clicked() {
+                            async_executor.execute_background(WFBackgroundTask::LoadSlotB);
+                        }
+                        let slot_b_path = params.slot_b_path.read().clone();
+                        if !slot_b_path.is_empty() {
+                            if ui.button("Clear").clicked() {
+                                async_executor.execute_background(WFBackgroundTask::ClearSlotB);
+                            }
+                            let filename = std::path::Path::new(&slot_b_path)
+                                .file_name()
+                                .and_then(|f| f.to_str())
+                                .unwrap_or("Unknown");
+                            ui.label(egui::RichText::new(filename).italics().size(10.0));
+                        }
+                        let mut align_crossings = *params.align_crossings.read();
+                        if ui
+                            .checkbox(&mut align_crossings, "Align zero crossings")
+                            .changed()
+                        {
+                            *params.align_crossings.write() = align_crossings;
+                            async_executor.execute_background(WFBackgroundTask::AlignSlotB);
+                        }
+                    });
+                    if table.slot_b().is_some() {
+                        ui.add_sized(
+                            egui::vec2(ui.available_width(), 20.0),
+                            widgets::ParamSlider::for_param(&params.table_morph, setter),
+                        );
+                    }
+
+                    ui.add_space(10.0);
+                    // --- ОВЕРСЕМПЛИНГ И ADAA ---
+                    ui.group(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Anti-alias:");
+                            let mut current_mode = params.anti_alias.value();
+                            for (val, label) in [(0, "Off"), (1, "ADAA 1"), (2, "ADAA 2")] {
+                                if ui.radio_value(&mut current_mode, val, label).changed() {
+                                    setter.begin_set_parameter(&params.anti_alias);
+                                    setter.set_parameter(&params.anti_alias, current_mode);
+                                    setter.end_set_parameter(&params.anti_alias);
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Oversampling:");
+                            let mut current_factor = params.oversampling.value();
+                            for (val, label) in
+                                [(0, "1x"), (1, "2x"), (2, "4x"), (3, "8x"), (4, "16x")]
+                            {
+                                if ui.radio_value(&mut current_factor, val, label).changed() {
+                                    setter.begin_set_parameter(&params.oversampling);
+                                    setter.set_parameter(&params.oversampling, current_factor);
+                                    setter.end_set_parameter(&params.oversampling);
+                                }
+                            }
+
+                            ui.separator();
+                            ui.label("Quality:");
+                            let mut current_quality = params.oversampling_quality.value();
+                            for (val, label) in [(0, "Low"), (1, "Medium"), (2, "High")] {
+                                if ui.radio_value(&mut current_quality, val, label).changed() {
+                                    setter.begin_set_parameter(&params.oversampling_quality);
+                                    setter.set_parameter(
+                                        &params.oversampling_quality,
+                                        current_quality,
+                                    );
+                                    setter.end_set_parameter(&params.oversampling_quality);
+                                }
+                            }
+                        });
+                    });
+
+                    ui.add_space(10.0);
+                    let mut linked = params.link.value();
+                    let mut mid_side = params.mid_side.value();
+                    ui.horizontal(|ui| {
+                        // В режиме Mid/Side у M и S всегда свои наборы параметров
+                        if ui
+                            .add_enabled(
+                                !mid_side,
+                                egui::Checkbox::new(&mut linked, "Link channels"),
+                            )
+                            .changed()
+                        {
+                            setter.begin_set_parameter(&params.link);
+                            setter.set_parameter(&params.link, linked);
+                            setter.end_set_parameter(&params.link);
+                        }
+                        if ui.checkbox(&mut mid_side, "Mid/Side").changed() {
+                            setter.begin_set_parameter(&params.mid_side);
+                            setter.set_parameter(&params.mid_side, mid_side);
+                            setter.end_set_parameter(&params.mid_side);
+                        }
+                        ui.separator();
+                        ui.toggle_value(show_smoothing, "Smoothing");
+                    });
+                    let (first, second) = if mid_side { ("M", "S") } else { ("L", "R") };
+
+                    ui.label(egui::RichText::new("Spread"));
                     ui.add_sized(
-                        slider_size,
-                        widgets::ParamSlider::for_param(&params.gain, setter),
+                        egui::vec2(ui.available_width(), 20.0),
+                        widgets::ParamSlider::for_param(&params.spread, setter),
                     );
                     ui.add_space(5.0);
-                    ui.label(egui::RichText::new("Phase"));
-                    ui.add_sized(
-                        slider_size,
-                        widgets::ParamSlider::for_param(&params.phase, setter),
-                    );
+
+                    // Слайдеры
+                    if linked && !mid_side {
+                        param_sliders(
+                            ui,
+                            setter,
+                            &[
+                                ("Dry/Wet", &params.dw),
+                                ("Drive", &params.gain),
+                                ("Phase", &params.phase),
+                            ],
+                        );
+                    } else {
+                        ui.columns(2, |columns| {
+                            param_sliders(
+                                &mut columns[0],
+                                setter,
+                                &[
+                                    (format!("Dry/Wet {first}").as_str(), &params.dw),
+                                    (format!("Drive {first}").as_str(), &params.gain),
+                                    (format!("Phase {first}").as_str(), &params.phase),
+                                ],
+                            );
+                            param_sliders(
+                                &mut columns[1],
+                                setter,
+                                &[
+                                    (format!("Dry/Wet {second}").as_str(), &params.dw_r),
+                                    (format!("Drive {second}").as_str(), &params.gain_r),
+                                    (format!("Phase {second}").as_str(), &params.phase_r),
+                                ],
+                            );
+                        });
+                    }
                 });
+
+                // --- НАСТРОЙКИ СГЛАЖИВАНИЯ ---
+                egui::Window::new("Smoothing")
+                    .open(show_smoothing)
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(egui_ctx, |ui| {
+                        let mut smoothing = *params.smoothing.read();
+                        egui::Grid::new("smoothing")
+                            .num_columns(3)
+                            .spacing([10.0, 4.0])
+                            .show(ui, |ui| {
+                                for (label, entry) in [
+                                    ("Drive", &mut smoothing.drive),
+                                    ("Phase", &mut smoothing.phase),
+                                    ("Dry/Wet", &mut smoothing.dry_wet),
+                                    ("Spread", &mut smoothing.spread),
+                                    ("Table Position", &mut smoothing.table_position),
+                                    ("Shape", &mut smoothing.shape),
+                                    ("Table Morph", &mut smoothing.table_morph),
+                                ] {
+                                    ui.label(label);
+                                    egui::ComboBox::from_id_salt(label)
+                                        .selected_text(smoothing_style_label(entry.style))
+                                        .show_ui(ui, |ui| {
+                                            for style in [
+                                                SmoothingStyle::Linear,
+                                                SmoothingStyle::Logarithmic,
+                                                SmoothingStyle::Exponential,
+                                            ] {
+                                                ui.selectable_value(
+                                                    &mut entry.style,
+                                                    style,
+                                                    smoothing_style_label(style),
+                                                );
+                                            }
+                                        });
+                                    ui.add(
+                                        egui::DragValue::new(&mut entry.time_ms)
+                                            .range(0.0..=1000.0)
+                                            .speed(0.5)
+                                            .suffix("ms"),
+                                    );
+                                    ui.end_row();
+                                }
+                            });
+                        ui.label(
+                            egui::RichText::new("Drive is smoothed in dB")
+                                .italics()
+                                .size(10.0),
+                        );
+                        if smoothing != *params.smoothing.read() {
+                            *params.smoothing.write() = smoothing;
+                        }
+                    });
             },
         )
     }
 }
 
+fn frame_size_label(frame_size: usize) -> String {
+    if frame_size == 0 {
+        "Auto".to_string()
+    } else {
+        frame_size.to_string()
+    }
+}
+
+fn normalization_label(normalization: Normalization) -> &'static str {
+    match normalization {
+        Normalization::None => "None",
+        Normalization::Peak => "Peak",
+        Normalization::Rms => "RMS",
+        Normalization::DcPeak => "Remove DC + peak",
+        Normalization::SymmetricPeak => "Symmetric peak",
+    }
+}
+
+fn table_channels_label(table_channels: TableChannels) -> String {
+    match table_channels {
+        TableChannels::Mix => "Mix to mono".to_string(),
+        TableChannels::Stereo => "Left / Right".to_string(),
+        TableChannels::Channel(index) => format!("Channel {}", index + 1),
+    }
+}
+
+fn smoothing_style_label(style: SmoothingStyle) -> &'static str {
+    match style {
+        SmoothingStyle::Linear => "Linear",
+        SmoothingStyle::Logarithmic => "Logarithmic",
+        SmoothingStyle::Exponential => "Exponential",
+    }
+}
+
+fn param_sliders(ui: &mut egui::Ui, setter: &ParamSetter, sliders: &[(&str, &FloatParam)]) {
+    let slider_size = egui::vec2(ui.available_width(), 20.0);
+    for (i, (label, param)) in sliders.iter().enumerate() {
+        if i > 0 {
+            ui.add_space(5.0);
+        }
+        ui.label(egui::RichText::new(*label));
+        ui.add_sized(slider_size, widgets::ParamSlider::for_param(*param, setter));
+    }
+}
+
+impl ClapPlugin for WF {
+    const CLAP_ID: &'static str = "com.gemma.wavefolder-distortion";
+    const CLAP_DESCRIPTION: Option<&'static str> = Some("Wavefolder distortion");
+    const CLAP_MANUAL_URL: Option<&'static str> = Some(Self::URL);
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[
+        ClapFeature::AudioEffect,
+        ClapFeature::Distortion,
+        ClapFeature::Mono,
+        ClapFeature::Stereo,
+        ClapFeature::Surround,
+    ];
+}
+
 impl Vst3Plugin for WF {
     const VST3_CLASS_ID: [u8; 16] = [
         98, 218, 94, 45, 78, 44, 74, 204, 167, 126, 143, 79, 37, 188, 235, 20,
@@ -422,4 +1251,5 @@ impl Vst3Plugin for WF {
     const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] = &[Vst3SubCategory::Distortion];
 }
 
+nih_export_clap!(WF);
 nih_export_vst3!(WF);
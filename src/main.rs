@@ -0,0 +1,7 @@
+use nih_plug::prelude::*;
+
+use wavefolder::WF;
+
+fn main() {
+    nih_export_standalone::<WF>();
+}
@@ -0,0 +1,95 @@
+// Полосоограниченные копии кадра таблицы для больших Drive: лишние гармоники обрезаются в спектре
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex;
+
+// Последний отсчет повторяет первый отсчет следующего периода, период frame_size - 1
+pub fn max_harmonic(frame_size: usize) -> usize {
+    frame_size.saturating_sub(1) / 2
+}
+
+// Уровень m хранит нижние max_harmonic >> m гармоник, последний - только основную
+pub fn level_count(frame_size: usize) -> usize {
+    let max_harmonic = max_harmonic(frame_size);
+    if max_harmonic < 2 {
+        0
+    } else {
+        max_harmonic.ilog2() as usize
+    }
+}
+
+// Все уровни подряд, каждый длиной frame.len()
+pub fn build_levels(frame: &[f32], planner: &mut FftPlanner<f32>) -> Vec<f32> {
+    let levels = level_count(frame.len());
+    let period = frame.len() - 1;
+    let mut levels_out = Vec::with_capacity(levels * frame.len());
+    if levels == 0 {
+        return levels_out;
+    }
+
+    let mut spectrum = frame[..period]
+        .iter()
+        .map(|&s| Complex::new(s, 0.0))
+        .collect::<Vec<_>>();
+    planner.plan_fft_forward(period).process(&mut spectrum);
+    let inverse = planner.plan_fft_inverse(period);
+
+    let mut buffer = vec![Complex::new(0.0, 0.0); period];
+    for level in 1..=levels {
+        let harmonics = max_harmonic(frame.len()) >> level;
+        // Бин k и зеркальный period - k - это одна и та же гармоника
+        for (k, (bin, source)) in buffer.iter_mut().zip(&spectrum).enumerate() {
+            let harmonic = k.min(period - k);
+            *bin = if harmonic <= harmonics {
+                *source
+            } else {
+                Complex::new(0.0, 0.0)
+            };
+        }
+        inverse.process(&mut buffer);
+
+        let scale = 1.0 / period as f32;
+        levels_out.extend(buffer.iter().map(|c| c.re * scale));
+        // Замыкаем период, как в исходной таблице
+        levels_out.push(buffer[0].re * scale);
+    }
+    levels_out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    #[test]
+    fn level_count_follows_harmonics() {
+        assert_eq!(level_count(0), 0);
+        assert_eq!(level_count(4), 0);
+        assert_eq!(level_count(5), 1);
+        assert_eq!(level_count(257), 7);
+        assert_eq!(level_count(2048), 9);
+    }
+
+    #[test]
+    fn levels_drop_upper_harmonics() {
+        // Основной тон плюс 20-я гармоника
+        let period = 128;
+        let frame = (0..=period)
+            .map(|i| {
+                let t = i as f32 / period as f32 * TAU;
+                t.sin() + 0.5 * (20.0 * t).sin()
+            })
+            .collect::<Vec<_>>();
+        let levels = build_levels(&frame, &mut FftPlanner::new());
+        assert_eq!(levels.len(), level_count(frame.len()) * frame.len());
+
+        let level = |m: usize| &levels[(m - 1) * frame.len()..m * frame.len()];
+        // Уровень 1 хранит 32 гармоники, уровень 2 - 16
+        for (i, (&kept, &source)) in level(1).iter().zip(&frame).enumerate() {
+            assert!((kept - source).abs() < 1.0e-4, "sample {}", i);
+        }
+        for (i, &filtered) in level(2).iter().enumerate() {
+            let t = i as f32 / period as f32 * TAU;
+            assert!((filtered - t.sin()).abs() < 1.0e-4, "sample {}", i);
+        }
+    }
+}
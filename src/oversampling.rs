@@ -0,0 +1,255 @@
+use std::f32::consts::PI;
+
+// 2x, 4x, 8x и 16x - до четырех ступеней 2x
+pub const MAX_STAGES: usize = 4;
+pub const MAX_FACTOR: usize = 1 << MAX_STAGES;
+
+// Длина FIR полуполосной ступени для Low/Medium/High: 4k + 3, без нулевых отводов в ветвях
+const QUALITY_TAPS: [usize; 3] = [15, 31, 63];
+const MAX_TAPS: usize = 63;
+
+pub fn latency_samples(stages: usize, quality: usize) -> u32 {
+    let stages = stages.min(MAX_STAGES);
+    ((cascade_delay(stages, quality) + align_delay(stages, quality)) >> stages) as u32
+}
+
+// Задержка каскада в отсчетах самой высокой частоты: пара фильтров ступени s дает taps - 1
+// отсчетов своей частоты, то есть (taps - 1) * 2^(stages - 1 - s). В сумме это обычно не
+// целое число отсчетов хоста (7 + 3.5 при двух ступенях)
+fn cascade_delay(stages: usize, quality: usize) -> usize {
+    let taps = QUALITY_TAPS[quality.min(QUALITY_TAPS.len() - 1)];
+    (taps - 1) * ((1 << stages) - 1)
+}
+
+// Добавочная задержка на самой высокой частоте, доводящая задержку до целого отсчета хоста
+fn align_delay(stages: usize, quality: usize) -> usize {
+    let factor = 1 << stages;
+    (factor - cascade_delay(stages, quality) % factor) % factor
+}
+
+// Полуполосный ФНЧ, разбитый на две полифазные ветви
+struct HalfBandFilter {
+    taps: Vec<f32>,
+    even: Vec<f32>,
+    odd: Vec<f32>,
+}
+
+impl HalfBandFilter {
+    fn new(len: usize) -> Self {
+        let center = (len - 1) as f32 / 2.0;
+        let mut taps = (0..len)
+            .map(|n| {
+                let t = n as f32 - center;
+                let sinc = if t == 0.0 {
+                    1.0
+                } else {
+                    (PI * t / 2.0).sin() / (PI * t / 2.0)
+                };
+                // Окно Блэкмана
+                let phase = 2.0 * PI * n as f32 / (len - 1) as f32;
+                let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+                sinc * window
+            })
+            .collect::<Vec<_>>();
+
+        // Единичное усиление на DC
+        let sum: f32 = taps.iter().sum();
+        taps.iter_mut().for_each(|t| *t /= sum);
+
+        let even = taps.iter().step_by(2).map(|t| t * 2.0).collect();
+        let odd = taps.iter().skip(1).step_by(2).map(|t| t * 2.0).collect();
+
+        Self { taps, even, odd }
+    }
+}
+
+// Кольцевой буфер записан дважды подряд, последние len отсчетов всегда идут подряд
+struct DelayLine {
+    buffer: Vec<f32>,
+    len: usize,
+    pos: usize,
+}
+
+impl DelayLine {
+    fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![0.0; len * 2],
+            len,
+            pos: 0,
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, x: f32) {
+        self.pos = if self.pos == 0 {
+            self.len - 1
+        } else {
+            self.pos - 1
+        };
+        self.buffer[self.pos] = x;
+        self.buffer[self.pos + self.len] = x;
+    }
+
+    #[inline]
+    fn history(&self) -> &[f32] {
+        &self.buffer[self.pos..self.pos + self.len]
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.pos = 0;
+    }
+}
+
+#[inline]
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+struct HalfBandStage {
+    up: DelayLine,
+    down: DelayLine,
+}
+
+impl HalfBandStage {
+    fn new() -> Self {
+        Self {
+            up: DelayLine::new(MAX_TAPS),
+            down: DelayLine::new(MAX_TAPS),
+        }
+    }
+
+    #[inline]
+    fn upsample(&mut self, filter: &HalfBandFilter, input: f32) -> [f32; 2] {
+        self.up.push(input);
+        let history = self.up.history();
+        [dot(&filter.even, history), dot(&filter.odd, history)]
+    }
+
+    #[inline]
+    fn downsample(&mut self, filter: &HalfBandFilter, input: [f32; 2]) -> f32 {
+        // Выход берем на четных отсчетах, чтобы задержка была целой
+        self.down.push(input[0]);
+        let output = dot(&filter.taps, self.down.history());
+        self.down.push(input[1]);
+        output
+    }
+
+    fn reset(&mut self) {
+        self.up.reset();
+        self.down.reset();
+    }
+}
+
+// Буферы выделяются заранее, process не выделяет память
+pub struct Oversampler {
+    stages: Vec<HalfBandStage>,
+    align: DelayLine,
+    filters: Vec<HalfBandFilter>,
+    buffer: Vec<f32>,
+    scratch: Vec<f32>,
+}
+
+impl Oversampler {
+    pub fn new(max_block_size: usize) -> Self {
+        Self {
+            stages: (0..MAX_STAGES).map(|_| HalfBandStage::new()).collect(),
+            align: DelayLine::new(MAX_FACTOR),
+            filters: QUALITY_TAPS
+                .iter()
+                .map(|&len| HalfBandFilter::new(len))
+                .collect(),
+            buffer: vec![0.0; max_block_size * MAX_FACTOR],
+            scratch: vec![0.0; max_block_size * MAX_FACTOR],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.stages.iter_mut().for_each(HalfBandStage::reset);
+        self.align.reset();
+    }
+
+    // block не длиннее max_block_size, с которым создан оверсемплер
+    pub fn process<F: FnMut(&mut [f32])>(
+        &mut self,
+        block: &mut [f32],
+        stages: usize,
+        quality: usize,
+        mut shaper: F,
+    ) {
+        let stages = stages.min(MAX_STAGES);
+        if stages == 0 {
+            shaper(block);
+            return;
+        }
+
+        let filter = &self.filters[quality.min(self.filters.len() - 1)];
+        let mut len = block.len();
+        self.buffer[..len].copy_from_slice(block);
+
+        for stage in self.stages[..stages].iter_mut() {
+            for i in 0..len {
+                let [even, odd] = stage.upsample(filter, self.buffer[i]);
+                self.scratch[2 * i] = even;
+                self.scratch[2 * i + 1] = odd;
+            }
+            std::mem::swap(&mut self.buffer, &mut self.scratch);
+            len *= 2;
+        }
+
+        let align = align_delay(stages, quality);
+        if align > 0 {
+            for sample in self.buffer[..len].iter_mut() {
+                self.align.push(*sample);
+                *sample = self.align.history()[align];
+            }
+        }
+
+        shaper(&mut self.buffer[..len]);
+
+        for stage in self.stages[..stages].iter_mut().rev() {
+            len /= 2;
+            for i in 0..len {
+                self.scratch[i] =
+                    stage.downsample(filter, [self.buffer[2 * i], self.buffer[2 * i + 1]]);
+            }
+            std::mem::swap(&mut self.buffer, &mut self.scratch);
+        }
+
+        block.copy_from_slice(&self.buffer[..len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_is_a_whole_number_of_samples() {
+        // Медленный синус проходит без искажений, выход - вход со сдвигом на latency_samples
+        let input = (0..512)
+            .map(|i| (2.0 * PI * 500.0 * i as f32 / 48000.0).sin())
+            .collect::<Vec<_>>();
+        for stages in 1..=MAX_STAGES {
+            for quality in 0..QUALITY_TAPS.len() {
+                let mut oversampler = Oversampler::new(input.len());
+                let mut output = input.clone();
+                oversampler.process(&mut output, stages, quality, |_| {});
+                let latency = latency_samples(stages, quality) as usize;
+                let error = output[latency..]
+                    .iter()
+                    .zip(&input)
+                    .skip(64)
+                    .map(|(o, i)| (o - i).abs())
+                    .fold(0.0, f32::max);
+                assert!(
+                    error < 2.0e-3,
+                    "{} stages, quality {}: {}",
+                    stages,
+                    quality,
+                    error
+                );
+            }
+        }
+    }
+}
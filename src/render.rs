@@ -0,0 +1,226 @@
+// Оффлайн-рендер WAV-файлов тем же кодом, что и WF::process
+use parking_lot::RwLock;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::engine::{
+    AntiAlias, Interpolation, Oversampling, OversamplingQuality, TableHandoff, WaveFolderEngine,
+    Waveform,
+};
+use crate::wav_reader;
+use crate::wavetable::Wavetable;
+
+pub use crate::wav_reader::{Normalization, TableChannels};
+
+// Единицы и диапазоны те же, что у параметров плагина
+pub struct RenderOptions {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub table: Option<PathBuf>,
+    pub table_b: Option<PathBuf>,
+    pub table_morph: f32,
+    pub align_crossings: bool,
+    // 0 - размер кадра из файла или один кадр
+    pub frame_size: usize,
+    pub table_channels: TableChannels,
+    pub normalization: Normalization,
+    pub table_position: f32,
+    // Drive в дБ, Phase в градусах
+    pub drive: f32,
+    pub phase: f32,
+    pub dry_wet: f32,
+    // 0 - синус, 1 - треугольник, 2 - пила, 3 - меандр, 4 - своя таблица, 5 - морф
+    pub waveform: i32,
+    pub shape: f32,
+    // 0 - ближайший, 1 - линейная, 2 - Эрмит, 3 - Катмулл-Ром, 4 и 5 - Лагранж 4 и 6, 6 - sinc
+    pub interpolation: i32,
+    pub band_limit: bool,
+    // 0 - выкл, 1 и 2 - порядок ADAA
+    pub anti_alias: i32,
+    // Число ступеней 2x
+    pub oversampling: usize,
+    // 0 - низкое, 1 - среднее, 2 - высокое
+    pub oversampling_quality: usize,
+    pub spread: f32,
+    pub mid_side: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            input: PathBuf::new(),
+            output: PathBuf::new(),
+            table: None,
+            table_b: None,
+            table_morph: 0.0,
+            align_crossings: false,
+            frame_size: 0,
+            table_channels: TableChannels::Mix,
+            normalization: Normalization::Peak,
+            table_position: 0.0,
+            drive: 0.0,
+            phase: 0.0,
+            dry_wet: 1.0,
+            waveform: 1,
+            shape: 0.0,
+            interpolation: 1,
+            band_limit: false,
+            anti_alias: 0,
+            oversampling: 0,
+            oversampling_quality: 1,
+            spread: 0.0,
+            mid_side: false,
+        }
+    }
+}
+
+// Задержка оверсемплинга компенсируется, выход совпадает со входом по отсчетам
+pub fn render_file(options: &RenderOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // Таблица по умолчанию такая же, как в плагине
+    let custom_waveform = TableHandoff::new(Wavetable::new(
+        (0..2048)
+            .map(|s| s as f32 / 1024.0 - 1.0)
+            .collect::<Vec<_>>(),
+        0,
+    ));
+    let zero_crossing_points = Arc::new(RwLock::new(vec![vec![0.5]]));
+    let load_options = wav_reader::LoadOptions {
+        frame_size: options.frame_size,
+        table_channels: options.table_channels,
+        normalization: options.normalization,
+    };
+    if let Some(table) = &options.table {
+        wav_reader::process_samples(
+            wav_reader::read_table(table)?,
+            load_options,
+            &custom_waveform,
+            &zero_crossing_points,
+        );
+    }
+    if let Some(table_b) = &options.table_b {
+        let table_b = wav_reader::build_table(wav_reader::read_table(table_b)?, load_options);
+        custom_waveform.publish_slot_b(Some(Arc::new(table_b)), options.align_crossings);
+    }
+
+    let mut reader = hound::WavReader::open(&options.input)?;
+    let spec = reader.spec();
+    let num_channels = spec.channels as usize;
+    let interleaved = wav_reader::wav_samples(&mut reader)?;
+
+    let mut engine = WaveFolderEngine::new(num_channels, spec.sample_rate as f32);
+    engine.set_drive(options.drive);
+    engine.set_phase(options.phase);
+    engine.set_dry_wet(options.dry_wet);
+    engine.set_spread(options.spread);
+    engine.set_table_position(options.table_position);
+    // Таблицу забираем до смены формы: во время кроссфейда от начальной формы она бы не пришла
+    engine.receive_table(&custom_waveform);
+    engine.set_waveform(Waveform::from_index(options.waveform));
+    engine.set_morph(options.shape);
+    engine.set_table_morph(options.table_morph);
+    engine.set_interpolation(Interpolation::from_index(options.interpolation));
+    engine.set_band_limit(options.band_limit);
+    engine.set_anti_alias(AntiAlias::from_index(options.anti_alias));
+    engine.set_oversampling(Oversampling::from_index(options.oversampling as i32));
+    engine.set_oversampling_quality(OversamplingQuality::from_index(
+        options.oversampling_quality as i32,
+    ));
+    engine.set_mid_side(options.mid_side);
+    // Значения постоянные, сглаживать нечего
+    engine.reset();
+    let latency = engine.latency() as usize;
+
+    // Разбираем по каналам и добавляем тишину на длину задержки фильтров
+    let num_frames = interleaved.len() / num_channels.max(1);
+    let mut channels = (0..num_channels)
+        .map(|channel| {
+            let mut samples = interleaved
+                .iter()
+                .skip(channel)
+                .step_by(num_channels)
+                .copied()
+                .collect::<Vec<_>>();
+            samples.resize(num_frames + latency, 0.0);
+            samples
+        })
+        .collect::<Vec<_>>();
+    let mut channel_slices = channels
+        .iter_mut()
+        .map(|c| c.as_mut_slice())
+        .collect::<Vec<_>>();
+
+    engine.process(&mut channel_slices);
+
+    let mut writer = hound::WavWriter::create(
+        &options.output,
+        hound::WavSpec {
+            channels: spec.channels,
+            sample_rate: spec.sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        },
+    )?;
+    for frame in latency..num_frames + latency {
+        for channel in &channels {
+            writer.write_sample(channel[frame])?;
+        }
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_with_the_given_table() {
+        let dir = std::env::temp_dir().join(format!("wavefolder_render_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Падающая пила с пиком ровно 1: нормализация ее не меняет, и она не похожа на встроенную
+        let table = (0..256).map(|i| 1.0 - i as f32 / 128.0).collect::<Vec<_>>();
+        let table_path = dir.join("table.f32");
+        let bytes = table
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect::<Vec<_>>();
+        std::fs::write(&table_path, bytes).unwrap();
+
+        let input = (0..64).map(|i| i as f32 / 64.0 - 0.5).collect::<Vec<_>>();
+        let input_path = dir.join("input.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 48000,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(&input_path, spec).unwrap();
+        input.iter().for_each(|s| writer.write_sample(*s).unwrap());
+        writer.finalize().unwrap();
+
+        let output_path = dir.join("output.wav");
+        render_file(&RenderOptions {
+            input: input_path,
+            output: output_path.clone(),
+            table: Some(table_path),
+            waveform: 4,
+            ..RenderOptions::default()
+        })
+        .unwrap();
+        let output = hound::WavReader::open(&output_path)
+            .unwrap()
+            .samples::<f32>()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+
+        let mut engine = WaveFolderEngine::new(1, 48000.0);
+        engine.load_table(table);
+        engine.set_waveform(Waveform::Custom);
+        engine.reset();
+        let mut expected = input;
+        engine.process_block(&mut expected);
+        assert_eq!(output, expected);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+
+// Время сглаживания параметров по умолчанию
+const DEFAULT_SMOOTHING_MS: f32 = 10.0;
+// Доля пути, которую экспоненциальное сглаживание проходит за заданное время
+const EXPONENTIAL_SETTLE: f32 = 0.9999;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SmoothingStyle {
+    #[default]
+    Linear,
+    // Постоянное отношение, линейно в логарифмическом масштабе; через ноль и от нуля - линейно
+    Logarithmic,
+    // Однополюсный фильтр; к концу времени сглаживания проходит 99.99% пути и встает на цель
+    Exponential,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Smoothing {
+    pub style: SmoothingStyle,
+    pub time_ms: f32,
+}
+
+impl Default for Smoothing {
+    fn default() -> Self {
+        Self {
+            style: SmoothingStyle::Linear,
+            time_ms: DEFAULT_SMOOTHING_MS,
+        }
+    }
+}
+
+// Сглаживание всех автоматизируемых параметров, хранится в состоянии плагина
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmoothingSettings {
+    pub drive: Smoothing,
+    pub phase: Smoothing,
+    pub dry_wet: Smoothing,
+    pub spread: Smoothing,
+    pub table_position: Smoothing,
+    pub shape: Smoothing,
+    pub table_morph: Smoothing,
+}
+
+impl SmoothingSettings {
+    pub fn entries_mut(&mut self) -> [&mut Smoothing; 7] {
+        [
+            &mut self.drive,
+            &mut self.phase,
+            &mut self.dry_wet,
+            &mut self.spread,
+            &mut self.table_position,
+            &mut self.shape,
+            &mut self.table_morph,
+        ]
+    }
+}
+
+// Сглаживание к последней цели, чтобы параметры не давали ступенек
+#[derive(Clone, Copy)]
+pub struct Smoother {
+    current: f32,
+    target: f32,
+    // Шаг (Linear), множитель (Logarithmic) или коэффициент (Exponential) текущего перехода
+    step: f32,
+    steps_left: u32,
+    length: u32,
+    style: SmoothingStyle,
+    // Стиль текущего перехода: логарифмический через ноль идет линейно
+    ramp: SmoothingStyle,
+}
+
+impl Smoother {
+    pub fn new(value: f32) -> Self {
+        Self {
+            current: value,
+            target: value,
+            step: 0.0,
+            steps_left: 0,
+            length: 0,
+            style: SmoothingStyle::Linear,
+            ramp: SmoothingStyle::Linear,
+        }
+    }
+
+    // Длина перехода в отсчетах, действует со следующей цели
+    pub fn set_length(&mut self, length: u32) {
+        self.length = length;
+    }
+
+    pub fn set_smoothing(&mut self, smoothing: Smoothing, sample_rate: f32) {
+        self.style = smoothing.style;
+        self.set_length((smoothing.time_ms.max(0.0) / 1000.0 * sample_rate).round() as u32);
+    }
+
+    pub fn set_target(&mut self, target: f32) {
+        if target == self.target {
+            return;
+        }
+
+        self.target = target;
+        if self.length == 0 {
+            self.snap();
+            return;
+        }
+
+        self.steps_left = self.length;
+        let length = self.length as f32;
+        self.ramp = match self.style {
+            SmoothingStyle::Logarithmic if self.current * target > 0.0 => {
+                self.step = (target / self.current).powf(1.0 / length);
+                SmoothingStyle::Logarithmic
+            }
+            SmoothingStyle::Exponential => {
+                self.step = 1.0 - (1.0 - EXPONENTIAL_SETTLE).powf(1.0 / length);
+                SmoothingStyle::Exponential
+            }
+            _ => {
+                self.step = (target - self.current) / length;
+                SmoothingStyle::Linear
+            }
+        };
+    }
+
+    // Идет ли еще переход к цели
+    pub fn is_ramping(&self) -> bool {
+        self.steps_left > 0
+    }
+
+    pub fn snap(&mut self) {
+        self.current = self.target;
+        self.steps_left = 0;
+    }
+
+    #[inline]
+    pub fn next(&mut self) -> f32 {
+        if self.steps_left > 0 {
+            self.steps_left -= 1;
+            self.current = if self.steps_left == 0 {
+                self.target
+            } else {
+                match self.ramp {
+                    SmoothingStyle::Linear => self.current + self.step,
+                    SmoothingStyle::Logarithmic => self.current * self.step,
+                    SmoothingStyle::Exponential => {
+                        self.current + (self.target - self.current) * self.step
+                    }
+                }
+            };
+        }
+        self.current
+    }
+
+    pub fn fill(&mut self, block: &mut [f32]) {
+        for value in block.iter_mut() {
+            *value = self.next();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(style: SmoothingStyle, from: f32, to: f32) -> Vec<f32> {
+        let mut smoother = Smoother::new(from);
+        smoother.set_smoothing(
+            Smoothing {
+                style,
+                time_ms: 1.0,
+            },
+            8000.0,
+        );
+        smoother.set_target(to);
+        let mut block = [0.0; 10];
+        smoother.fill(&mut block);
+        block.to_vec()
+    }
+
+    #[test]
+    fn every_style_reaches_the_target_in_time() {
+        for style in [
+            SmoothingStyle::Linear,
+            SmoothingStyle::Logarithmic,
+            SmoothingStyle::Exponential,
+        ] {
+            // 1 мс при 8 кГц - 8 отсчетов
+            let values = ramp(style, 1.0, 100.0);
+            assert!(values[6] < 100.0, "{:?}: {:?}", style, values);
+            assert_eq!(values[7..], [100.0; 3], "{:?}", style);
+            assert!(values.windows(2).all(|w| w[0] <= w[1]), "{:?}", style);
+        }
+    }
+
+    #[test]
+    fn styles_shape_the_ramp() {
+        let linear = ramp(SmoothingStyle::Linear, 1.0, 100.0);
+        let logarithmic = ramp(SmoothingStyle::Logarithmic, 1.0, 100.0);
+        let exponential = ramp(SmoothingStyle::Exponential, 1.0, 100.0);
+        // Середина перехода: 50.5 по прямой, 10 в логарифмической шкале
+        assert!((linear[3] - 50.5).abs() < 1.0e-3);
+        assert!((logarithmic[3] - 10.0).abs() < 1.0e-3);
+        assert!(exponential[3] > linear[3]);
+
+        // Через ноль логарифмический переход идет по прямой
+        assert_eq!(
+            ramp(SmoothingStyle::Logarithmic, -1.0, 1.0),
+            ramp(SmoothingStyle::Linear, -1.0, 1.0)
+        );
+    }
+}
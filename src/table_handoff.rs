@@ -0,0 +1,219 @@
+// Передача таблицы из фонового потока в аудиопоток без блокировок и без освобождения памяти в аудиопотоке
+use parking_lot::{Mutex, RwLock};
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::table_morph;
+use crate::wavetable::Wavetable;
+
+// Передача таблицы от загрузчика аудиопотоку без ожиданий и освобождения памяти в аудиопотоке:
+// аудиопоток забирает таблицу одним атомарным обменом, а старую оставляет в retired.
+// Слоты A и B публикуются по отдельности и собираются здесь
+pub struct TableHandoff {
+    // Исходные таблицы слотов; заодно упорядочивает несколько публикующих потоков
+    slots: Mutex<Slots>,
+    // Текущая таблица для редактора и фоновых задач; аудиопоток ее не читает
+    current: RwLock<Arc<Wavetable>>,
+    // Опубликованная, но еще не забранная аудиопотоком таблица (Arc::into_raw)
+    pending: AtomicPtr<Wavetable>,
+    // Таблица, которую аудиопоток заменил; освобождается вне аудиопотока
+    retired: AtomicPtr<Wavetable>,
+}
+
+struct Slots {
+    a: Arc<Wavetable>,
+    b: Option<Arc<Wavetable>>,
+    // Выравнивать B по нулям A
+    aligned: bool,
+}
+
+impl Slots {
+    // Слот A, с присоединенным слотом B, если он есть
+    fn combine(&self) -> Arc<Wavetable> {
+        match &self.b {
+            Some(b) => Arc::new(table_morph::attach_slot_b(&self.a, Some(b), self.aligned)),
+            None => self.a.clone(),
+        }
+    }
+}
+
+impl TableHandoff {
+    pub fn new(table: Wavetable) -> Self {
+        let table = Arc::new(table);
+        Self {
+            pending: AtomicPtr::new(Arc::into_raw(table.clone()).cast_mut()),
+            current: RwLock::new(table.clone()),
+            retired: AtomicPtr::new(ptr::null_mut()),
+            slots: Mutex::new(Slots {
+                a: table,
+                b: None,
+                aligned: false,
+            }),
+        }
+    }
+
+    // Не для аудиопотока: берет блокировку и клонирует Arc
+    pub fn current(&self) -> Arc<Wavetable> {
+        self.current.read().clone()
+    }
+
+    // Слот A без слота B
+    pub fn slot_a(&self) -> Arc<Wavetable> {
+        self.slots.lock().a.clone()
+    }
+
+    // Слот B до выравнивания
+    pub fn slot_b(&self) -> Option<Arc<Wavetable>> {
+        self.slots.lock().b.clone()
+    }
+
+    // Неполученная аудиопотоком таблица освобождается здесь
+    pub fn publish(&self, table: Wavetable) {
+        let mut slots = self.slots.lock();
+        slots.a = Arc::new(table);
+        self.hand_over(slots.combine());
+    }
+
+    // С aligned слот B пересобирается при каждой смене любого слота
+    pub fn publish_slot_b(&self, table: Option<Arc<Wavetable>>, aligned: bool) {
+        let mut slots = self.slots.lock();
+        slots.b = table;
+        slots.aligned = aligned;
+        self.hand_over(slots.combine());
+    }
+
+    fn hand_over(&self, table: Arc<Wavetable>) {
+        let mut current = self.current.write();
+        *current = table.clone();
+        self.collect_retired();
+        let stale = self
+            .pending
+            .swap(Arc::into_raw(table).cast_mut(), Ordering::AcqRel);
+        release(stale);
+    }
+
+    pub fn collect_retired(&self) {
+        release(self.retired.swap(ptr::null_mut(), Ordering::AcqRel));
+    }
+
+    // false, если нового нет или вытесненная таблица еще не собрана;
+    // тогда таблица будет взята при следующем вызове
+    #[inline]
+    pub fn exchange(&self, table: &mut Arc<Wavetable>) -> bool {
+        // Ненулевым retired делает только аудиопоток, так что после проверки слот останется пустым
+        if !self.retired.load(Ordering::Acquire).is_null() {
+            return false;
+        }
+        let new = self.pending.swap(ptr::null_mut(), Ordering::AcqRel);
+        if new.is_null() {
+            return false;
+        }
+        // SAFETY: pending содержит только указатели из Arc::into_raw, и swap передал владение нам
+        let new = unsafe { Arc::from_raw(new) };
+        let old = std::mem::replace(table, new);
+        self.retired
+            .store(Arc::into_raw(old).cast_mut(), Ordering::Release);
+        true
+    }
+}
+
+impl Drop for TableHandoff {
+    fn drop(&mut self) {
+        release(*self.pending.get_mut());
+        release(*self.retired.get_mut());
+    }
+}
+
+fn release(table: *mut Wavetable) {
+    if !table.is_null() {
+        // SAFETY: указатель получен из Arc::into_raw и больше нигде не хранится
+        drop(unsafe { Arc::from_raw(table) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(value: f32) -> Wavetable {
+        Wavetable::new(vec![value; 8], 0)
+    }
+
+    #[test]
+    fn audio_thread_gets_the_latest_table() {
+        let handoff = TableHandoff::new(table(0.0));
+        let mut audio_table = Arc::new(table(-1.0));
+        assert!(handoff.exchange(&mut audio_table));
+        assert_eq!(audio_table.samples()[0], 0.0);
+        assert!(!handoff.exchange(&mut audio_table));
+
+        // Из двух публикаций подряд аудиопоток получает только последнюю
+        handoff.publish(table(1.0));
+        handoff.publish(table(2.0));
+        assert!(handoff.exchange(&mut audio_table));
+        assert_eq!(audio_table.samples()[0], 2.0);
+        assert_eq!(handoff.current().samples()[0], 2.0);
+    }
+
+    #[test]
+    fn waits_for_the_retired_table_to_be_collected() {
+        let handoff = TableHandoff::new(table(0.0));
+        let mut audio_table = Arc::new(table(-1.0));
+        let replaced = Arc::downgrade(&audio_table);
+        assert!(handoff.exchange(&mut audio_table));
+        // Вытесненная таблица ждет в слоте, аудиопоток ее не освобождает
+        assert!(replaced.upgrade().is_some());
+
+        // Новая таблица в обход publish, чтобы слот не собрался
+        let pending = Arc::into_raw(Arc::new(table(1.0))).cast_mut();
+        handoff.pending.store(pending, Ordering::Release);
+        assert!(!handoff.exchange(&mut audio_table));
+        assert_eq!(audio_table.samples()[0], 0.0);
+
+        handoff.collect_retired();
+        assert!(replaced.upgrade().is_none());
+        assert!(handoff.exchange(&mut audio_table));
+        assert_eq!(audio_table.samples()[0], 1.0);
+    }
+
+    #[test]
+    fn slot_b_survives_a_new_slot_a() {
+        let handoff = TableHandoff::new(table(0.0));
+        handoff.publish_slot_b(Some(Arc::new(table(2.0))), false);
+        handoff.publish(table(1.0));
+        let current = handoff.current();
+        assert_eq!(current.samples()[0], 1.0);
+        assert_eq!(current.slot_b().unwrap().samples()[0], 2.0);
+        assert_eq!(handoff.slot_b().unwrap().samples()[0], 2.0);
+
+        handoff.publish_slot_b(None, false);
+        let current = handoff.current();
+        assert_eq!(current.samples()[0], 1.0);
+        assert!(current.slot_b().is_none());
+    }
+
+    #[test]
+    fn slot_b_reuses_the_built_slot_a() {
+        let handoff = TableHandoff::new(table(0.0));
+        handoff.publish_slot_b(Some(Arc::new(table(2.0))), false);
+        handoff.publish(table(1.0));
+        // Таблица аудиопотока читает те же кадры, что и слоты, без пересборки
+        let current = handoff.current();
+        let slot_b = handoff.slot_b().unwrap();
+        assert_eq!(
+            current.samples().as_ptr(),
+            handoff.slot_a().samples().as_ptr()
+        );
+        assert_eq!(
+            current.slot_b().unwrap().samples().as_ptr(),
+            slot_b.samples().as_ptr()
+        );
+
+        handoff.publish_slot_b(Some(slot_b), true);
+        assert_eq!(
+            handoff.current().samples().as_ptr(),
+            handoff.slot_a().samples().as_ptr()
+        );
+    }
+}
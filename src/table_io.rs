@@ -0,0 +1,218 @@
+// Таблицы в виде текста (CSV, по значению в строке или пары x,y) и сырых f32 little-endian
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use crate::wavetable::Wavetable;
+
+// Сколько отсчетов получается из пар x,y - как у таблицы по умолчанию
+const RESAMPLED_LEN: usize = 2048;
+// Комментарий с размером кадра, чтобы многокадровая таблица пережила экспорт и импорт
+const FRAME_SIZE_COMMENT: &str = "# frame_size=";
+
+#[derive(Debug)]
+pub struct TableFormatError(String);
+
+impl fmt::Display for TableFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for TableFormatError {}
+
+// Одно значение в строке или пары x,y; строки с # и заголовок пропускаются
+pub fn read_text(path: &Path) -> Result<(Vec<f32>, Option<usize>), Box<dyn Error + Send + Sync>> {
+    Ok(parse_text(&std::fs::read_to_string(path)?)?)
+}
+
+fn parse_text(text: &str) -> Result<(Vec<f32>, Option<usize>), TableFormatError> {
+    let mut frame_size = None;
+    let mut values = Vec::new();
+    let mut pairs = Vec::new();
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some(size) = line.strip_prefix(FRAME_SIZE_COMMENT) {
+            frame_size = size.trim().parse().ok();
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields = line
+            .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+            .filter(|f| !f.is_empty())
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<_>, _>>();
+        let fields = match fields {
+            Ok(fields) => fields,
+            // Заголовок вида "x,y" допускаем только первой строкой с данными
+            Err(_) if values.is_empty() && pairs.is_empty() => continue,
+            Err(_) => {
+                return Err(TableFormatError(format!(
+                    "line {}: not a number: {}",
+                    line_index + 1,
+                    line
+                )));
+            }
+        };
+
+        match fields[..] {
+            [y] if pairs.is_empty() => values.push(y),
+            [x, y] if values.is_empty() => pairs.push((x, y)),
+            _ => {
+                return Err(TableFormatError(format!(
+                    "line {}: expected one value or an x,y pair in every line",
+                    line_index + 1
+                )));
+            }
+        }
+    }
+
+    if !pairs.is_empty() {
+        values = resample_pairs(pairs);
+    }
+    if values.is_empty() {
+        return Err(TableFormatError("no values in the table".to_string()));
+    }
+    Ok((values, frame_size))
+}
+
+// Линейно по точкам x,y на RESAMPLED_LEN отсчетов по всему диапазону x
+fn resample_pairs(mut pairs: Vec<(f32, f32)>) -> Vec<f32> {
+    pairs.retain(|(x, y)| x.is_finite() && y.is_finite());
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (Some(&(x_min, _)), Some(&(x_max, _))) = (pairs.first(), pairs.last()) else {
+        return Vec::new();
+    };
+    if x_max <= x_min {
+        return vec![pairs[0].1];
+    }
+
+    let mut segment = 0;
+    (0..RESAMPLED_LEN)
+        .map(|i| {
+            let x = x_min + (x_max - x_min) * i as f32 / (RESAMPLED_LEN - 1) as f32;
+            while segment + 2 < pairs.len() && pairs[segment + 1].0 < x {
+                segment += 1;
+            }
+            let (x0, y0) = pairs[segment];
+            let (x1, y1) = pairs[segment + 1];
+            if x1 > x0 {
+                y0 + (y1 - y0) * ((x - x0) / (x1 - x0)).clamp(0.0, 1.0)
+            } else {
+                y1
+            }
+        })
+        .collect()
+}
+
+// Сырые f32 little-endian без заголовка
+pub fn read_raw(path: &Path) -> Result<Vec<f32>, Box<dyn Error + Send + Sync>> {
+    let data = std::fs::read(path)?;
+    if data.len() % 4 != 0 {
+        return Err(Box::new(TableFormatError(
+            "raw f32 file length is not a multiple of 4 bytes".to_string(),
+        )));
+    }
+    Ok(data
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+// Формат по расширению
+pub fn export_table(path: &Path, table: &Wavetable) -> std::io::Result<()> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "f32" | "raw" => write_raw(path, table.samples()),
+        _ => write_text(path, table.samples(), table.frame_size()),
+    }
+}
+
+// .csv и .txt отличаются только расширением
+pub fn write_text(path: &Path, samples: &[f32], frame_size: usize) -> std::io::Result<()> {
+    std::fs::write(path, format_text(samples, frame_size))
+}
+
+fn format_text(samples: &[f32], frame_size: usize) -> String {
+    let mut text = String::new();
+    if frame_size > 0 && frame_size < samples.len() {
+        text.push_str(&format!("{}{}\n", FRAME_SIZE_COMMENT, frame_size));
+    }
+    // Формат {:?} печатает f32 без потери точности
+    for sample in samples {
+        text.push_str(&format!("{:?}\n", sample));
+    }
+    text
+}
+
+pub fn write_raw(path: &Path, samples: &[f32]) -> std::io::Result<()> {
+    std::fs::write(
+        path,
+        samples
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect::<Vec<_>>(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_one_value_per_line() {
+        let (values, frame_size) = parse_text("# generated\n0.5\n\n-1\n1e-1\n").unwrap();
+        assert_eq!(values, vec![0.5, -1.0, 0.1]);
+        assert_eq!(frame_size, None);
+    }
+
+    #[test]
+    fn resamples_pairs_with_header() {
+        let (values, _) = parse_text("x;y\n1, 1\n0 -1\n2\t-1\n").unwrap();
+        assert_eq!(values.len(), RESAMPLED_LEN);
+        assert_eq!(values[0], -1.0);
+        assert_eq!(values[RESAMPLED_LEN - 1], -1.0);
+        let peak = values.iter().cloned().fold(f32::MIN, f32::max);
+        assert!((peak - 1.0).abs() < 1.0e-3);
+        assert!(values.windows(2).all(|w| (w[1] - w[0]).abs() < 0.01));
+    }
+
+    #[test]
+    fn rejects_mixed_and_broken_lines() {
+        assert!(parse_text("").is_err());
+        assert!(parse_text("0.5\n1,2\n").is_err());
+        assert!(parse_text("0.5\nabc\n").is_err());
+        assert!(parse_text("1 2 3\n").is_err());
+    }
+
+    #[test]
+    fn text_round_trip_keeps_samples_and_frames() {
+        let samples = vec![0.1f32, -0.3333333, 1.0, f32::MIN_POSITIVE];
+        let (values, frame_size) = parse_text(&format_text(&samples, 2)).unwrap();
+        assert_eq!(values, samples);
+        assert_eq!(frame_size, Some(2));
+
+        let (_, frame_size) = parse_text(&format_text(&samples, 4)).unwrap();
+        assert_eq!(frame_size, None);
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let path = std::env::temp_dir().join(format!("wavefolder_raw_{}.f32", std::process::id()));
+        let samples = vec![0.25f32, -1.0, 0.0, 0.75];
+        write_raw(&path, &samples).unwrap();
+        assert_eq!(read_raw(&path).unwrap(), samples);
+
+        std::fs::write(&path, [0u8; 5]).unwrap();
+        assert!(read_raw(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
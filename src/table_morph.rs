@@ -0,0 +1,260 @@
+// Слоты A и B для Table Morph и выравнивание таблицы B по нулям таблицы A
+use crate::utils::lookup_custom;
+use crate::wavetable::Wavetable;
+use crate::zero_crossing_detector::zero_crosing_points;
+
+// Таблица A с присоединенной таблицей B, по каналам. Стерео в любом из слотов дает стерео.
+// Кадры A и невыровненной B берутся готовыми; с aligned каждый кадр B подгоняется
+// align_crossings под кадр A в той же относительной позиции и строится заново.
+pub fn attach_slot_b(a: &Wavetable, b: Option<&Wavetable>, aligned: bool) -> Wavetable {
+    let channel = |index| {
+        let a = a.channel(index);
+        let b = b.map(|b| {
+            let b = b.channel(index);
+            if aligned {
+                Wavetable::new(align_table(a, b), b.frame_size())
+            } else {
+                b.share_frames()
+            }
+        });
+        a.share_frames().with_slot_b(b)
+    };
+
+    let table = channel(0);
+    if a.is_stereo() || b.is_some_and(Wavetable::is_stereo) {
+        table.with_right(channel(1))
+    } else {
+        table
+    }
+}
+
+// Каждый кадр table выравнивается по кадру reference на том же относительном месте
+fn align_table(reference: &Wavetable, table: &Wavetable) -> Vec<f32> {
+    let reference_last = reference.frame_count() - 1;
+    let table_last = table.frame_count() - 1;
+    (0..=table_last)
+        .flat_map(|index| {
+            let reference_index = if table_last == 0 {
+                0
+            } else {
+                (index as f32 * reference_last as f32 / table_last as f32).round() as usize
+            };
+            align_crossings(reference.frame(reference_index), table.frame(index))
+        })
+        .collect()
+}
+
+// Сдвигает фазу table так, чтобы ее нули легли на нули reference и смесь не гасилась у нулей.
+// При равном числе нулей каждый отрезок растягивается на парный, восходящие к восходящим;
+// иначе таблица только поворачивается
+pub fn align_crossings(reference: &[f32], table: &[f32]) -> Vec<f32> {
+    let reference_points = played_crossings(reference);
+    let points = played_crossings(table);
+    if reference_points.is_empty() || points.is_empty() {
+        return table.to_vec();
+    }
+
+    let len = table.len();
+    let offset = (reference_points.len() == points.len())
+        .then(|| best_pairing(reference, &reference_points, table, &points))
+        .flatten();
+    let phase = |index: usize| {
+        let p = index as f32 / (len - 1) as f32;
+        match offset {
+            Some(offset) => warp(p, &reference_points, &points, offset),
+            None => {
+                // Разное число нулей: только поворот по первому восходящему переходу
+                let first_rising = |table, points: &[f32]| {
+                    points
+                        .iter()
+                        .copied()
+                        .find(|&p| is_rising(table, p))
+                        .unwrap_or(points[0])
+                };
+                p + first_rising(table, &points) - first_rising(reference, &reference_points)
+            }
+        }
+    };
+
+    (0..len)
+        .map(|index| sample_at(table, phase(index)))
+        .collect()
+}
+
+// Нули в фазах проигрывания: lookup_custom ставит отсчет i на фазу i / (len - 1)
+fn played_crossings(table: &[f32]) -> Vec<f32> {
+    let mut points = Vec::new();
+    zero_crosing_points(table, &mut points);
+    let scale = table.len() as f32 / (table.len() as f32 - 1.0);
+    points.iter_mut().for_each(|point| *point *= scale);
+    points
+}
+
+// Поворот нулей с совпадающими направлениями и наименьшим суммарным расстоянием
+fn best_pairing(
+    reference: &[f32],
+    reference_points: &[f32],
+    table: &[f32],
+    points: &[f32],
+) -> Option<usize> {
+    let n = points.len();
+    (0..n)
+        .filter(|&offset| {
+            (0..n).all(|i| {
+                is_rising(reference, reference_points[i])
+                    == is_rising(table, points[(i + offset) % n])
+            })
+        })
+        .map(|offset| {
+            let distance = (0..n)
+                .map(|i| {
+                    let d = circular_distance(reference_points[i], points[(i + offset) % n]);
+                    d.min(1.0 - d)
+                })
+                .sum::<f32>();
+            (offset, distance)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(offset, _)| offset)
+}
+
+// Фаза table, которая попадает на фазу p опорной таблицы
+fn warp(p: f32, reference_points: &[f32], points: &[f32], offset: usize) -> f32 {
+    let n = points.len();
+    // Отрезок опорной таблицы, в который попала фаза; точки отсортированы
+    let segment = reference_points
+        .iter()
+        .rposition(|&point| point <= p)
+        .unwrap_or(n - 1);
+    let span = |points: &[f32], start: usize| {
+        let length = circular_distance(points[start], points[(start + 1) % n]);
+        // Единственный ноль: отрезок - весь период
+        if length == 0.0 { 1.0 } else { length }
+    };
+    let fraction =
+        circular_distance(reference_points[segment], p) / span(reference_points, segment);
+    let start = (segment + offset) % n;
+    points[start] + fraction * span(points, start)
+}
+
+// Расстояние вперед от from до to на окружности длины 1
+fn circular_distance(from: f32, to: f32) -> f32 {
+    (to - from).rem_euclid(1.0)
+}
+
+fn is_rising(table: &[f32], point: f32) -> bool {
+    if point == 0.0 {
+        // Переход через конец таблицы детектор ставит в 0
+        return table[0] > 0.0;
+    }
+    // Переход между отсчетами index и index + 1; фаза 1 - это конец последнего отрезка
+    let index = ((point * (table.len() - 1) as f32) as usize).min(table.len() - 2);
+    table[index] < 0.0
+}
+
+// Значение при фазе p (период 1) так, как его проигрывает движок
+fn sample_at(table: &[f32], p: f32) -> f32 {
+    lookup_custom(table, 4.0 * p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    fn sine(len: usize, shift: f32, harmonic: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                // Полотсчета сдвига, чтобы ни один отсчет не попал ровно в ноль
+                let p = (i as f32 + 0.5) / len as f32;
+                (TAU * (p + shift)).sin() + harmonic * (TAU * 3.0 * p).sin()
+            })
+            .collect()
+    }
+
+    fn crossings(table: &[f32]) -> Vec<f32> {
+        let mut points = Vec::new();
+        zero_crosing_points(table, &mut points);
+        points
+    }
+
+    #[test]
+    fn crossings_land_on_the_reference() {
+        let reference = sine(256, 0.0, 0.0);
+        // Сдвинутый синус с гармоникой: нули другие, но их столько же
+        let table = sine(512, 0.2, 0.3);
+        let aligned = align_crossings(&reference, &table);
+        assert_eq!(aligned.len(), table.len());
+        assert_eq!(crossings(&aligned).len(), crossings(&reference).len());
+        for (a, r) in crossings(&aligned).iter().zip(&crossings(&reference)) {
+            let d = circular_distance(*a, *r);
+            assert!(d.min(1.0 - d) < 0.01, "{} vs {}", a, r);
+        }
+        // Восходящий переход остается восходящим
+        assert!(aligned[16] > 0.0 && aligned[500] < 0.0);
+    }
+
+    #[test]
+    fn crossings_meet_when_played() {
+        // Короткие таблицы: период проигрывания len - 1 заметно короче len
+        let reference = sine(16, 0.0, 0.0);
+        let table = sine(24, 0.2, 0.3);
+        let aligned = align_crossings(&reference, &table);
+        let played = |table: &[f32]| {
+            let values = (0..4096)
+                .map(|i| lookup_custom(table, 4.0 * i as f32 / 4096.0))
+                .collect::<Vec<_>>();
+            crossings(&values)
+        };
+        let reference_points = played(&reference);
+        let points = played(&aligned);
+        assert_eq!(points.len(), reference_points.len());
+        for (a, r) in points.iter().zip(&reference_points) {
+            let d = circular_distance(*a, *r);
+            assert!(d.min(1.0 - d) < 0.005, "{} vs {}", a, r);
+        }
+    }
+
+    #[test]
+    fn different_crossing_counts_only_rotate() {
+        let reference = sine(64, 0.0, 0.0);
+        // Третья гармоника: шесть нулей против двух
+        let table = (0..64)
+            .map(|i| (TAU * (3.0 * (i as f32 + 0.5) / 64.0 + 0.25)).sin())
+            .collect::<Vec<_>>();
+        let aligned = align_crossings(&reference, &table);
+        // Восходящий ноль ложится на фазу 0, где восходит опорная таблица
+        let distance = played_crossings(&aligned)
+            .into_iter()
+            .filter(|&p| is_rising(&aligned, p))
+            .map(|p| {
+                let d = circular_distance(p, 0.0);
+                d.min(1.0 - d)
+            })
+            .fold(f32::INFINITY, f32::min);
+        assert!(distance < 0.02, "{}", distance);
+    }
+
+    #[test]
+    fn tables_without_crossings_stay_as_they_are() {
+        let reference = sine(32, 0.0, 0.0);
+        let table = vec![0.5; 32];
+        assert_eq!(align_crossings(&reference, &table), table);
+        assert_eq!(align_crossings(&table, &reference), reference);
+    }
+
+    #[test]
+    fn slot_b_follows_the_channels() {
+        let a = Wavetable::new(sine(64, 0.0, 0.0), 0);
+        let b = Wavetable::new(vec![0.5; 32], 0).with_right(Wavetable::new(vec![-0.5; 32], 0));
+        let table = attach_slot_b(&a, Some(&b), false);
+        assert!(table.is_stereo());
+        assert_eq!(table.channel(1).samples(), a.samples());
+        assert_eq!(table.slot_b().unwrap().samples(), &[0.5; 32]);
+        assert_eq!(table.channel(1).slot_b().unwrap().samples(), &[-0.5; 32]);
+
+        let table = attach_slot_b(&a, None, true);
+        assert!(!table.is_stereo());
+        assert!(table.slot_b().is_none());
+    }
+}
@@ -0,0 +1,148 @@
+// Таблица внутри состояния плагина: сессия открывается без исходного файла
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use parking_lot::RwLock;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::Arc;
+
+use crate::table_handoff::TableHandoff;
+use crate::wav_reader;
+use crate::wavetable::Wavetable;
+
+// Больше не встраиваем: 256 кадров по 1024 отсчета - около 1.4 МБ base64 на канал
+pub const EMBED_MAX_SAMPLES: usize = 1 << 18;
+
+// Таблица в состоянии плагина: отсчеты после нормализации и разбивка на кадры
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddedTable {
+    samples: Samples,
+    // Правая таблица, если файл загружен как стерео
+    right: Option<Samples>,
+    frame_size: usize,
+    // Число каналов исходного файла, для выбора каналов в редакторе
+    file_channels: usize,
+}
+
+// Отсчеты в JSON как base64 от f32 little-endian: в разы короче массива чисел и без потерь
+#[derive(Clone, Debug, PartialEq)]
+struct Samples(Vec<f32>);
+
+impl Serialize for Samples {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = self
+            .0
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect::<Vec<_>>();
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+}
+
+impl<'de> Deserialize<'de> for Samples {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = STANDARD
+            .decode(String::deserialize(deserializer)?)
+            .map_err(D::Error::custom)?;
+        if bytes.len() % 4 != 0 {
+            return Err(D::Error::custom("sample data is not a whole number of f32"));
+        }
+        Ok(Samples(
+            bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        ))
+    }
+}
+
+impl EmbeddedTable {
+    // None для таблиц длиннее EMBED_MAX_SAMPLES - у них остается только путь
+    pub fn capture(table: &Wavetable, file_channels: usize) -> Option<Self> {
+        if table.samples().len() > EMBED_MAX_SAMPLES {
+            return None;
+        }
+        Some(Self {
+            samples: Samples(table.samples().to_vec()),
+            right: table
+                .is_stereo()
+                .then(|| Samples(table.channel(1).samples().to_vec())),
+            frame_size: table.frame_size(),
+            file_channels,
+        })
+    }
+
+    pub fn file_channels(&self) -> usize {
+        self.file_channels
+    }
+
+    pub fn to_wavetable(&self) -> Wavetable {
+        let table = Wavetable::new(self.samples.0.clone(), self.frame_size);
+        match &self.right {
+            Some(right) => table.with_right(Wavetable::new(right.0.clone(), self.frame_size)),
+            None => table,
+        }
+    }
+
+    pub fn restore(
+        &self,
+        custom_waveform: &TableHandoff,
+        zero_crossing_points: &Arc<RwLock<Vec<Vec<f32>>>>,
+    ) {
+        let table = self.to_wavetable();
+        let points = wav_reader::frame_zero_crossings(&table);
+        custom_waveform.publish(table);
+        *zero_crossing_points.write() = points;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn survives_a_json_round_trip() {
+        let left = (0..32).map(|i| (i as f32 * 0.37).sin()).collect::<Vec<_>>();
+        let right = left.iter().map(|s| -s * 0.5).collect::<Vec<_>>();
+        let table = Wavetable::new(left.clone(), 16).with_right(Wavetable::new(right.clone(), 16));
+        let embedded = EmbeddedTable::capture(&table, 2).unwrap();
+
+        // nih-plug хранит поля #[persist] в JSON
+        let json = serde_json::to_string(&embedded).unwrap();
+        // base64 от 4 байт на отсчет, а не десятичные числа
+        assert!(
+            json.len() < (left.len() + right.len()) * 6 + 100,
+            "{}",
+            json
+        );
+        let restored = serde_json::from_str::<EmbeddedTable>(&json).unwrap();
+        assert_eq!(restored, embedded);
+
+        let custom_waveform = TableHandoff::new(Wavetable::new(Vec::new(), 0));
+        let zero_crossing_points = Arc::new(RwLock::new(Vec::new()));
+        restored.restore(&custom_waveform, &zero_crossing_points);
+        let table = custom_waveform.current();
+        assert_eq!(table.samples(), &left[..]);
+        assert_eq!(table.channel(1).samples(), &right[..]);
+        assert_eq!(table.frame_count(), 2);
+        // Нули найдены заново для каждого кадра
+        assert_eq!(
+            *zero_crossing_points.read(),
+            wav_reader::frame_zero_crossings(&table)
+        );
+        assert_eq!(zero_crossing_points.read().len(), 2);
+        assert_eq!(restored.file_channels(), 2);
+    }
+
+    #[test]
+    fn large_tables_are_not_embedded() {
+        let table = Wavetable::new(vec![0.5; EMBED_MAX_SAMPLES + 1], 0);
+        assert!(EmbeddedTable::capture(&table, 1).is_none());
+        assert!(
+            serde_json::from_str::<EmbeddedTable>(
+                r#"{"samples":"AAA","right":null,"frame_size":0,"file_channels":1}"#
+            )
+            .is_err()
+        );
+    }
+}
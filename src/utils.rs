@@ -20,7 +20,7 @@ pub fn lookup_custom(table: &[f32], x: f32) -> f32 {
 
     let index_f = normalized_x * (len - 1.0);
 
-    let index_low = (index_f.floor() as usize).max(0);
+    let index_low = index_f.floor() as usize;
     let index_high = if index_low + 1 < table.len() {
         index_low + 1
     } else {
@@ -82,3 +82,101 @@ pub fn xfader(a: f32, b: f32, ratio: f32) -> f32 {
     a * (ratio * std::f32::consts::FRAC_PI_2).cos()
         + b * (ratio * std::f32::consts::FRAC_PI_2).sin()
 }
+
+#[inline]
+pub fn encode_mid_side(left: &mut [f32], right: &mut [f32]) {
+    for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+        let mid = (*l + *r) * 0.5;
+        let side = (*l - *r) * 0.5;
+        *l = mid;
+        *r = side;
+    }
+}
+
+#[inline]
+pub fn decode_mid_side(mid: &mut [f32], side: &mut [f32]) {
+    for (m, s) in mid.iter_mut().zip(side.iter_mut()) {
+        let left = *m + *s;
+        let right = *m - *s;
+        *m = left;
+        *s = right;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn finite_table() -> impl Strategy<Value = Vec<f32>> {
+        prop::collection::vec(-1.0f32..=1.0, 2..256)
+    }
+
+    #[test]
+    fn lookup_custom_short_tables_are_silent() {
+        for x in [0.0, 1.5, -3.0, 1.0e6] {
+            assert_eq!(lookup_custom(&[], x), 0.0);
+            assert_eq!(lookup_custom(&[0.7], x), 0.0);
+        }
+    }
+
+    #[test]
+    fn lookup_custom_non_finite_input_is_silent() {
+        let table = [0.5, -0.25, 1.0];
+        for x in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(lookup_custom(&table, x), 0.0);
+        }
+    }
+
+    #[test]
+    fn xfader_nan_is_silent() {
+        assert_eq!(xfader(f32::NAN, 1.0, 0.5), 0.0);
+        assert_eq!(xfader(1.0, f32::NAN, 0.5), 0.0);
+        assert_eq!(xfader(1.0, 1.0, f32::NAN), 0.0);
+    }
+
+    proptest! {
+        #[test]
+        fn lookup_custom_stays_within_table(table in finite_table(), x in any::<f32>()) {
+            let y = lookup_custom(&table, x);
+            if x.is_finite() {
+                let min = table.iter().copied().fold(f32::INFINITY, f32::min);
+                let max = table.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                prop_assert!(y >= min - 1.0e-6 && y <= max + 1.0e-6, "{} not in [{}; {}]", y, min, max);
+            } else {
+                prop_assert_eq!(y, 0.0);
+            }
+        }
+
+        #[test]
+        fn lookup_custom_has_period_4(table in finite_table(), x in -100.0f32..100.0) {
+            // У границы периода округление x + 4 может перескочить на другой конец таблицы
+            let position = x.rem_euclid(4.0);
+            prop_assume!(position > 1.0e-3 && position < 4.0 - 1.0e-3);
+            let a = lookup_custom(&table, x);
+            let b = lookup_custom(&table, x + 4.0);
+            // Сдвиг на период меняет x в последних битах, поэтому сравниваем с допуском
+            let slope = table.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0, f32::max);
+            let step = 4.0 / (table.len() - 1) as f32;
+            prop_assert!((a - b).abs() <= slope / step * 1.0e-4 + 1.0e-5, "{} != {}", a, b);
+        }
+
+        #[test]
+        fn xfader_endpoints(a in -10.0f32..10.0, b in -10.0f32..10.0) {
+            prop_assert!((xfader(a, b, 0.0) - a).abs() <= 1.0e-6);
+            prop_assert!((xfader(a, b, 1.0) - b).abs() <= 1.0e-5);
+        }
+
+        #[test]
+        fn xfader_is_bounded(a in -10.0f32..10.0, b in -10.0f32..10.0, ratio in 0.0f32..=1.0) {
+            // Равномощный кроссфейд: cos^2 + sin^2 = 1
+            let bound = (a * a + b * b).sqrt();
+            prop_assert!(xfader(a, b, ratio).abs() <= bound + 1.0e-5);
+        }
+
+        #[test]
+        fn xfader_finite_for_finite_input(a in -1.0e30f32..1.0e30, b in -1.0e30f32..1.0e30, ratio in 0.0f32..=1.0) {
+            prop_assert!(xfader(a, b, ratio).is_finite());
+        }
+    }
+}
@@ -1,41 +1,702 @@
 use nih_plug::nih_log;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::table_handoff::TableHandoff;
+use crate::wavetable::Wavetable;
+
+pub const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "wav", "flac", "ogg", "mp3", "wt", "csv", "txt", "f32", "raw",
+];
+
+// Самая длинная таблица на канал: 256 кадров по 4096
+pub const MAX_TABLE_LEN: usize = 1 << 20;
+// Глубина поиска потерянного файла в папках библиотеки
+const LOCATE_MAX_DEPTH: usize = 4;
+// Сколько папок просматривается всего, чтобы поиск от корня диска не шел бесконечно
+const LOCATE_MAX_FOLDERS: usize = 10_000;
+
+// Банк WaveEdit: 64 кадра по 256 отсчетов в одном WAV
+const WAVEEDIT_FRAME_SIZE: usize = 256;
+const WAVEEDIT_FRAME_COUNT: usize = 64;
+
+// Какие каналы многоканального файла становятся таблицей
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TableChannels {
+    #[default]
+    Mix,
+    // Канал с нуля; если каналов меньше - последний
+    Channel(usize),
+    // Первые два канала - отдельные таблицы для левого и правого
+    Stereo,
+}
+
+// Масштабирование таблицы при загрузке
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Normalization {
+    None,
+    #[default]
+    Peak,
+    // RMS как у синуса полной амплитуды, пики могут выйти за 1
+    Rms,
+    DcPeak,
+    SymmetricPeak,
+}
+
+// Настройки загрузки, хранятся в состоянии плагина
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoadOptions {
+    // 0 - размер кадра из файла или один кадр
+    pub frame_size: usize,
+    pub table_channels: TableChannels,
+    pub normalization: Normalization,
+}
+
+pub struct TableFile {
+    // Отсчеты всех каналов вперемешку
+    pub samples: Vec<f32>,
+    pub channels: usize,
+    pub frame_size: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    NotFound(PathBuf),
+    UnsupportedFormat(String),
+    Empty,
+    // Отсчетов на канал
+    TooLarge(usize),
+    Decode(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::NotFound(path) => write!(f, "File not found: {}", path.display()),
+            LoadError::UnsupportedFormat(extension) if extension.is_empty() => {
+                write!(f, "Unsupported file format")
+            }
+            LoadError::UnsupportedFormat(extension) => {
+                write!(f, "Unsupported file format: .{}", extension)
+            }
+            LoadError::Empty => write!(f, "The file contains no samples"),
+            LoadError::TooLarge(len) => write!(
+                f,
+                "The table is too large: {} samples, at most {} are supported",
+                len, MAX_TABLE_LEN
+            ),
+            LoadError::Decode(message) => write!(f, "Could not decode the file: {}", message),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+pub fn read_table(path: &Path) -> Result<TableFile, LoadError> {
+    if !path.is_file() {
+        return Err(LoadError::NotFound(path.to_path_buf()));
+    }
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if !SUPPORTED_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(LoadError::UnsupportedFormat(extension));
+    }
+
+    let file = decode_table(path, &extension).map_err(|err| {
+        match err.downcast_ref::<symphonia::core::errors::Error>() {
+            // Расширение знакомое, но внутри формат, который symphonia не понимает
+            Some(symphonia::core::errors::Error::Unsupported(_)) => {
+                LoadError::UnsupportedFormat(extension.clone())
+            }
+            _ => LoadError::Decode(err.to_string()),
+        }
+    })?;
+    let len = file.samples.len() / file.channels;
+    if len == 0 {
+        return Err(LoadError::Empty);
+    }
+    if len > MAX_TABLE_LEN {
+        return Err(LoadError::TooLarge(len));
+    }
+    Ok(file)
+}
+
+fn decode_table(path: &Path, extension: &str) -> Result<TableFile, Box<dyn Error + Send + Sync>> {
+    let mono = |(samples, frame_size)| TableFile {
+        samples,
+        channels: 1,
+        frame_size,
+    };
+    match extension {
+        "wav" => {
+            let mut reader = hound::WavReader::open(path)?;
+            let channels = (reader.spec().channels as usize).max(1);
+            let samples = wav_samples(&mut reader)?;
+            let frame_size = clm_frame_size(path).or_else(|| {
+                (samples.len() / channels == WAVEEDIT_FRAME_SIZE * WAVEEDIT_FRAME_COUNT)
+                    .then_some(WAVEEDIT_FRAME_SIZE)
+            });
+            Ok(TableFile {
+                samples,
+                channels,
+                frame_size,
+            })
+        }
+        "wt" => {
+            let (samples, frame_size) = crate::wt_reader::read_wt(path)?;
+            Ok(mono((samples, Some(frame_size))))
+        }
+        "csv" | "txt" => Ok(mono(crate::table_io::read_text(path)?)),
+        "f32" | "raw" => Ok(mono((crate::table_io::read_raw(path)?, None))),
+        _ => {
+            let (samples, channels) = crate::decoder::decode_file(path)?;
+            Ok(TableFile {
+                samples,
+                channels,
+                frame_size: None,
+            })
+        }
+    }
+}
+
+// Размер кадра Serum/Vital: строка `<!>2048 ...` в чанке `clm `
+fn clm_frame_size(path: &Path) -> Option<usize> {
+    let data = std::fs::read(path).ok()?;
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return None;
+    }
+
+    // Идем по чанкам RIFF; чанки нечетной длины выравниваются на байт
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let len = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().ok()?) as usize;
+        let body = &data[pos + 8..(pos + 8 + len).min(data.len())];
+        if id == b"clm " {
+            let text = String::from_utf8_lossy(body);
+            let digits = text
+                .strip_prefix("<!>")?
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>();
+            return digits.parse().ok().filter(|&size| size > 0);
+        }
+        pos += 8 + len + (len & 1);
+    }
+    None
+}
+
+// Возвращает число каналов файла
 pub fn process_wav_from_path(
     path: &str,
-    custom_waveform: &Arc<RwLock<Arc<Vec<f32>>>>,
-    zero_crossing_points: &Arc<RwLock<Vec<f32>>>,
-) {
-    if let Ok(reader) = hound::WavReader::open(path) {
-        process_wav_reader(reader, custom_waveform, zero_crossing_points);
-    } else {
-        nih_log!("Failed to open wav at: {}", path);
+    options: LoadOptions,
+    custom_waveform: &TableHandoff,
+    zero_crossing_points: &Arc<RwLock<Vec<Vec<f32>>>>,
+) -> Result<usize, LoadError> {
+    let path = Path::new(path);
+    match read_table(path) {
+        Ok(file) => {
+            let channels = file.channels;
+            process_samples(file, options, custom_waveform, zero_crossing_points);
+            Ok(channels)
+        }
+        Err(err) => {
+            nih_log!("Failed to open {}: {}", path.display(), err);
+            Err(err)
+        }
     }
 }
 
-pub fn process_wav_reader(
-    mut reader: hound::WavReader<std::io::BufReader<std::fs::File>>,
-    custom_waveform: &Arc<RwLock<Arc<Vec<f32>>>>,
-    zero_crossing_points: &Arc<RwLock<Vec<f32>>>,
-) {
-    let mut samples: Vec<f32> = match reader.spec().sample_format {
-        hound::SampleFormat::Float => reader.samples::<f32>().map(|s| s.unwrap_or(0.0)).collect(),
-        hound::SampleFormat::Int => reader
-            .samples::<i32>()
-            .map(|s| s.unwrap_or(0) as f32)
-            .collect(),
+// Где искать пропавший файл: папка библиотеки, затем сохранившиеся папки его прежнего пути, ближние первыми
+pub fn locate_roots(missing: &Path, library_root: &str) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if !library_root.is_empty() {
+        roots.push(PathBuf::from(library_root));
+    }
+    let ancestors = missing
+        .ancestors()
+        .skip(1)
+        .filter(|folder| !folder.as_os_str().is_empty() && folder.is_dir())
+        .take(LOCATE_MAX_DEPTH);
+    for folder in ancestors {
+        if !roots.iter().any(|root| root == folder) {
+            roots.push(folder.to_path_buf());
+        }
+    }
+    roots
+}
+
+pub fn locate_file(missing: &Path, roots: &[PathBuf]) -> Option<PathBuf> {
+    let file_name = missing.file_name()?;
+    // Обход в ширину, чтобы файл рядом с корнем находился раньше глубоко вложенного
+    let mut folders = roots
+        .iter()
+        .map(|root| (root.clone(), 0))
+        .collect::<std::collections::VecDeque<_>>();
+    let mut visited = 0;
+    while let Some((folder, depth)) = folders.pop_front() {
+        visited += 1;
+        if visited > LOCATE_MAX_FOLDERS {
+            break;
+        }
+        let candidate = folder.join(file_name);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if depth >= LOCATE_MAX_DEPTH {
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(&folder) else {
+            continue;
+        };
+        let mut subfolders = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>();
+        subfolders.sort();
+        folders.extend(subfolders.into_iter().map(|path| (path, depth + 1)));
+    }
+    None
+}
+
+// Отсчеты WAV в f32; оборванный или битый файл - ошибка, а не тишина
+pub fn wav_samples<R: std::io::Read>(
+    reader: &mut hound::WavReader<R>,
+) -> Result<Vec<f32>, hound::Error> {
+    let spec = reader.spec();
+    match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect(),
+        hound::SampleFormat::Int => {
+            // Целые приводим к -1..1 по разрядности, как это делают редакторы
+            let scale = (1i64 << (spec.bits_per_sample.clamp(1, 32) - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect()
+        }
+    }
+}
+
+// Одна таблица, или две для Stereo многоканального файла
+fn select_channels(
+    mut samples: Vec<f32>,
+    channels: usize,
+    table_channels: TableChannels,
+) -> Vec<Vec<f32>> {
+    if channels <= 1 {
+        return vec![samples];
+    }
+    // Неполный последний набор отсчетов отбрасываем, чтобы каналы были одной длины
+    samples.truncate(samples.len() / channels * channels);
+    let channel = |index: usize| {
+        samples
+            .iter()
+            .skip(index)
+            .step_by(channels)
+            .copied()
+            .collect::<Vec<_>>()
     };
+    match table_channels {
+        TableChannels::Mix => vec![
+            samples
+                .chunks_exact(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                .collect(),
+        ],
+        TableChannels::Channel(index) => vec![channel(index.min(channels - 1))],
+        TableChannels::Stereo => vec![channel(0), channel(1)],
+    }
+}
 
-    // Нормализация
-    let max_value = samples.iter().fold(0f32, |acc, s: &f32| acc.max(s.abs()));
-    if max_value > 0.0 {
-        samples.iter_mut().for_each(|s| *s /= max_value);
+// У каналов стерео таблицы общие усиление и смещение, чтобы сохранить баланс
+fn normalize(tables: &mut [Vec<f32>], normalization: Normalization) {
+    let count = tables.iter().map(Vec::len).sum::<usize>();
+    if count == 0 {
+        return;
     }
+    let peak = |tables: &[Vec<f32>]| {
+        tables
+            .iter()
+            .flatten()
+            .fold(0f32, |acc, s: &f32| acc.max(s.abs()))
+    };
 
-    let mut new_zero_crossing_points = Vec::new();
-    crate::zero_crossing_detector::zero_crosing_points(&samples, &mut new_zero_crossing_points);
+    let (offset, gain) = match normalization {
+        Normalization::None => return,
+        Normalization::Peak => (0.0, 1.0 / peak(tables)),
+        Normalization::Rms => {
+            let mean_square = tables
+                .iter()
+                .flatten()
+                .map(|&s| s as f64 * s as f64)
+                .sum::<f64>()
+                / count as f64;
+            (
+                0.0,
+                std::f32::consts::FRAC_1_SQRT_2 / mean_square.sqrt() as f32,
+            )
+        }
+        Normalization::DcPeak => {
+            let mean = tables.iter().flatten().map(|&s| s as f64).sum::<f64>() / count as f64;
+            let mean = mean as f32;
+            let peak = tables
+                .iter()
+                .flatten()
+                .fold(0f32, |acc, s| acc.max((s - mean).abs()));
+            (mean, 1.0 / peak)
+        }
+        Normalization::SymmetricPeak => {
+            let (min, max) = tables
+                .iter()
+                .flatten()
+                .fold((f32::MAX, f32::MIN), |(min, max), &s| {
+                    (min.min(s), max.max(s))
+                });
+            ((max + min) / 2.0, 2.0 / (max - min))
+        }
+    };
+    // Тишина и постоянный сигнал дают бесконечный множитель; их оставляем как есть
+    if gain.is_finite() {
+        tables
+            .iter_mut()
+            .flatten()
+            .for_each(|s| *s = (*s - offset) * gain);
+    }
+}
 
-    *custom_waveform.write() = Arc::new(samples);
+pub fn build_table(file: TableFile, options: LoadOptions) -> Wavetable {
+    // Размер кадра из настроек важнее указанного в файле
+    let frame_size = if options.frame_size > 0 {
+        options.frame_size
+    } else {
+        file.frame_size.unwrap_or(0)
+    };
+    let mut tables = select_channels(file.samples, file.channels, options.table_channels);
+    normalize(&mut tables, options.normalization);
+
+    // Интегралы для ADAA считаются здесь же, в фоновом потоке
+    let mut tables = tables
+        .into_iter()
+        .map(|samples| Wavetable::new(samples, frame_size));
+    let mut table = tables
+        .next()
+        .unwrap_or_else(|| Wavetable::new(Vec::new(), 0));
+    if let Some(right) = tables.next() {
+        table = table.with_right(right);
+    }
+    table
+}
+
+pub fn process_samples(
+    file: TableFile,
+    options: LoadOptions,
+    custom_waveform: &TableHandoff,
+    zero_crossing_points: &Arc<RwLock<Vec<Vec<f32>>>>,
+) {
+    let table = build_table(file, options);
+    let new_zero_crossing_points = frame_zero_crossings(&table);
+    custom_waveform.publish(table);
     *zero_crossing_points.write() = new_zero_crossing_points;
 }
+
+// Нули ищем в каждом кадре отдельно, редактор показывает ближайший к позиции
+pub fn frame_zero_crossings(table: &Wavetable) -> Vec<Vec<f32>> {
+    (0..table.frame_count())
+        .map(|index| {
+            let mut points = Vec::new();
+            crate::zero_crossing_detector::zero_crosing_points(table.frame(index), &mut points);
+            points
+        })
+        .collect()
+}
+
+// Настройки загрузки те же, что у слота A
+pub fn process_slot_b_from_path(
+    path: &str,
+    options: LoadOptions,
+    aligned: bool,
+    custom_waveform: &TableHandoff,
+) -> Result<(), LoadError> {
+    let path = Path::new(path);
+    match read_table(path) {
+        Ok(file) => {
+            custom_waveform.publish_slot_b(Some(Arc::new(build_table(file, options))), aligned);
+            Ok(())
+        }
+        Err(err) => {
+            nih_log!("Failed to open {}: {}", path.display(), err);
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(name: &str, chunks: &[(&[u8; 4], &[u8])]) -> std::path::PathBuf {
+        let mut body = b"WAVE".to_vec();
+        for (id, data) in chunks {
+            body.extend_from_slice(*id);
+            body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            body.extend_from_slice(data);
+            if data.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        file.extend_from_slice(&body);
+
+        let path =
+            std::env::temp_dir().join(format!("wavefolder_{}_{}.wav", name, std::process::id()));
+        std::fs::write(&path, file).unwrap();
+        path
+    }
+
+    #[test]
+    fn selects_channels_of_interleaved_samples() {
+        let samples = vec![1.0, -1.0, 0.5, 0.0, 0.25, 0.75, 9.0];
+        assert_eq!(
+            select_channels(samples.clone(), 2, TableChannels::Mix),
+            vec![vec![0.0, 0.25, 0.5]]
+        );
+        assert_eq!(
+            select_channels(samples.clone(), 2, TableChannels::Channel(1)),
+            vec![vec![-1.0, 0.0, 0.75]]
+        );
+        // Несуществующий канал заменяется последним
+        assert_eq!(
+            select_channels(samples.clone(), 2, TableChannels::Channel(5)),
+            vec![vec![-1.0, 0.0, 0.75]]
+        );
+        assert_eq!(
+            select_channels(samples.clone(), 2, TableChannels::Stereo),
+            vec![vec![1.0, 0.5, 0.25], vec![-1.0, 0.0, 0.75]]
+        );
+        // Моно файл остается одной таблицей в любом режиме
+        assert_eq!(
+            select_channels(samples.clone(), 1, TableChannels::Stereo),
+            vec![samples]
+        );
+    }
+
+    #[test]
+    fn stereo_tables_share_normalization() {
+        let custom_waveform = TableHandoff::new(Wavetable::new(Vec::new(), 0));
+        let zero_crossing_points = Arc::new(RwLock::new(Vec::new()));
+        let file = TableFile {
+            samples: vec![0.5, -0.25, -0.5, 0.25, 0.5, -0.25],
+            channels: 2,
+            frame_size: None,
+        };
+        let options = LoadOptions {
+            table_channels: TableChannels::Stereo,
+            ..Default::default()
+        };
+        process_samples(file, options, &custom_waveform, &zero_crossing_points);
+
+        let table = custom_waveform.current();
+        assert!(table.is_stereo());
+        assert_eq!(table.channel(0).samples(), &[1.0, -1.0, 1.0]);
+        assert_eq!(table.channel(1).samples(), &[-0.5, 0.5, -0.5]);
+        assert_eq!(zero_crossing_points.read().len(), 1);
+    }
+
+    #[test]
+    fn normalization_modes() {
+        let normalized = |samples: &[f32], normalization| {
+            let mut tables = vec![samples.to_vec()];
+            normalize(&mut tables, normalization);
+            tables.remove(0)
+        };
+        let table = [0.5, 0.1, -0.25, 0.1];
+        assert_eq!(normalized(&table, Normalization::None), table);
+        assert_eq!(
+            normalized(&table, Normalization::Peak),
+            [1.0, 0.2, -0.5, 0.2]
+        );
+        let assert_close = |actual: Vec<f32>, expected: [f32; 4]| {
+            for (a, e) in actual.iter().zip(expected) {
+                assert!((a - e).abs() < 1.0e-6, "{:?} vs {:?}", actual, expected);
+            }
+        };
+        assert_close(
+            normalized(&[0.6, 0.1, -0.2, -0.1], Normalization::DcPeak),
+            [1.0, 0.0, -0.6, -0.4],
+        );
+        assert_close(
+            normalized(&table, Normalization::SymmetricPeak),
+            [1.0, -0.2 / 3.0, -1.0, -0.2 / 3.0],
+        );
+
+        // Синус любой амплитуды по RMS выходит в полную шкалу
+        let sine = (0..64)
+            .map(|i| 0.3 * (i as f32 / 64.0 * std::f32::consts::TAU).sin())
+            .collect::<Vec<_>>();
+        let peak = normalized(&sine, Normalization::Rms)
+            .iter()
+            .fold(0f32, |acc, s| acc.max(s.abs()));
+        assert!((peak - 1.0).abs() < 1.0e-4, "{}", peak);
+
+        // Тишина не превращается в NaN
+        for mode in [
+            Normalization::Peak,
+            Normalization::Rms,
+            Normalization::DcPeak,
+            Normalization::SymmetricPeak,
+        ] {
+            assert_eq!(normalized(&[0.0; 4], mode), [0.0; 4]);
+        }
+        assert_eq!(normalized(&[0.3; 4], Normalization::DcPeak), [0.3; 4]);
+    }
+
+    #[test]
+    fn converts_integer_samples_by_bit_depth() {
+        let path =
+            std::env::temp_dir().join(format!("wavefolder_int16_{}.wav", std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in [16384i16, -32768, 0, 8192] {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let file = read_table(&path).unwrap();
+        assert_eq!(file.samples, vec![0.5, -1.0, 0.0, 0.25]);
+        assert_eq!(file.channels, 1);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn truncated_wav_is_an_error() {
+        // Заголовок обещает больше отсчетов, чем есть в файле
+        let path =
+            std::env::temp_dir().join(format!("wavefolder_truncated_{}.wav", std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in [16384i16, -32768, 0, 8192] {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        assert!(matches!(
+            read_table(&path).err(),
+            Some(LoadError::Decode(_))
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_errors_are_typed() {
+        let dir = std::env::temp_dir().join(format!("wavefolder_errors_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let missing = dir.join("missing.wav");
+        assert_eq!(
+            read_table(&missing).err(),
+            Some(LoadError::NotFound(missing))
+        );
+
+        let unsupported = dir.join("table.aiff");
+        std::fs::write(&unsupported, [0u8; 16]).unwrap();
+        assert_eq!(
+            read_table(&unsupported).err(),
+            Some(LoadError::UnsupportedFormat("aiff".to_string()))
+        );
+
+        let empty = dir.join("empty.f32");
+        std::fs::write(&empty, []).unwrap();
+        assert_eq!(read_table(&empty).err(), Some(LoadError::Empty));
+
+        let too_large = dir.join("large.f32");
+        std::fs::write(&too_large, vec![0u8; (MAX_TABLE_LEN + 1) * 4]).unwrap();
+        assert_eq!(
+            read_table(&too_large).err(),
+            Some(LoadError::TooLarge(MAX_TABLE_LEN + 1))
+        );
+
+        let broken = dir.join("broken.wav");
+        std::fs::write(&broken, b"not a wav file").unwrap();
+        assert!(matches!(
+            read_table(&broken).err(),
+            Some(LoadError::Decode(_))
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn locates_a_moved_file_by_name() {
+        let root = std::env::temp_dir().join(format!("wavefolder_locate_{}", std::process::id()));
+        let nested = root.join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(nested.join("table.wav"), [0u8; 4]).unwrap();
+
+        let missing = Path::new("/old/samples/table.wav");
+        assert_eq!(
+            locate_file(missing, &[root.join("nothing"), root.clone()]),
+            Some(nested.join("table.wav"))
+        );
+        assert_eq!(
+            locate_file(Path::new("/old/other.wav"), std::slice::from_ref(&root)),
+            None
+        );
+
+        // Папка проекта на месте, а файл переложен в соседнюю папку
+        let missing = root.join("a").join("samples").join("table.wav");
+        let roots = locate_roots(&missing, "");
+        assert_eq!(roots[0], root.join("a"));
+        assert_eq!(
+            locate_file(&missing, &roots),
+            Some(nested.join("table.wav"))
+        );
+        // Папка библиотеки просматривается первой
+        assert_eq!(locate_roots(&missing, "/library")[0], Path::new("/library"));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn reads_frame_size_from_clm_chunk() {
+        let path = write_temp(
+            "clm",
+            &[
+                (b"odd ", b"x"),
+                (b"clm ", b"<!>1024 10000000 wavetable (www.xferrecords.com)"),
+                (b"data", &[0; 4]),
+            ],
+        );
+        assert_eq!(clm_frame_size(&path), Some(1024));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn no_clm_chunk_means_single_frame() {
+        let path = write_temp("no_clm", &[(b"data", &[0; 4])]);
+        assert_eq!(clm_frame_size(&path), None);
+        std::fs::remove_file(path).unwrap();
+
+        let path = write_temp("bad_clm", &[(b"clm ", b"2048")]);
+        assert_eq!(clm_frame_size(&path), None);
+        std::fs::remove_file(path).unwrap();
+    }
+}
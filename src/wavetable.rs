@@ -0,0 +1,301 @@
+// Таблица из одного или нескольких кадров одинаковой длины (wavetable-банки Serum/Vital)
+use std::sync::Arc;
+
+use crate::adaa::{Shape, TableIntegrals};
+use crate::engine::Interpolation;
+use crate::interpolation;
+use crate::mipmap;
+
+// Своя форма: кадры одного размера с интегралами ADAA и мип-уровнями.
+// Однопериодный файл - таблица из одного кадра
+pub struct Wavetable {
+    // Кадры с интегралами и mip-уровнями; общие у копий из share_frames
+    frames: Arc<Frames>,
+    // Отдельная таблица для правого канала, если файл загружен как стерео
+    right: Option<Box<Wavetable>>,
+    // Таблица слота B, с которой смешивает Table Morph
+    slot_b: Option<Box<Wavetable>>,
+}
+
+struct Frames {
+    samples: Vec<f32>,
+    frame_size: usize,
+    integrals: Vec<TableIntegrals>,
+    // Уровни всех кадров подряд: [кадр][уровень 1..=mip_levels][отсчет]
+    mipmaps: Vec<f32>,
+    mip_levels: usize,
+}
+
+impl Wavetable {
+    // Неполный последний кадр отбрасывается; frame_size 0 или длиннее таблицы - один кадр
+    pub fn new(mut samples: Vec<f32>, frame_size: usize) -> Self {
+        let frame_size = if frame_size == 0 || frame_size > samples.len() {
+            samples.len()
+        } else {
+            frame_size
+        };
+        if let Some(frame_count) = samples.len().checked_div(frame_size) {
+            samples.truncate(frame_count * frame_size);
+        }
+
+        let integrals = if frame_size == 0 {
+            vec![TableIntegrals::new(&[])]
+        } else {
+            samples
+                .chunks(frame_size)
+                .map(TableIntegrals::new)
+                .collect()
+        };
+
+        // FFT считается здесь, то есть в фоновом потоке загрузки
+        let mip_levels = mipmap::level_count(frame_size);
+        let mut planner = rustfft::FftPlanner::new();
+        let mipmaps = if mip_levels == 0 {
+            Vec::new()
+        } else {
+            samples
+                .chunks(frame_size)
+                .flat_map(|frame| mipmap::build_levels(frame, &mut planner))
+                .collect()
+        };
+
+        Self {
+            frames: Arc::new(Frames {
+                samples,
+                frame_size,
+                integrals,
+                mipmaps,
+                mip_levels,
+            }),
+            right: None,
+            slot_b: None,
+        }
+    }
+
+    // Те же кадры без правой таблицы и слота B; данные общие, ничего не пересчитывается
+    pub fn share_frames(&self) -> Wavetable {
+        Self {
+            frames: self.frames.clone(),
+            right: None,
+            slot_b: None,
+        }
+    }
+
+    // right - для нечетных (правых) каналов, эта таблица - для четных
+    pub fn with_right(mut self, right: Wavetable) -> Self {
+        self.right = Some(Box::new(right));
+        self
+    }
+
+    // У стерео таблицы свой слот B в каждом канале
+    pub fn with_slot_b(mut self, slot_b: Option<Wavetable>) -> Self {
+        self.slot_b = slot_b.map(Box::new);
+        self
+    }
+
+    #[inline]
+    pub fn slot_b(&self) -> Option<&Wavetable> {
+        self.slot_b.as_deref()
+    }
+
+    pub fn is_stereo(&self) -> bool {
+        self.right.is_some()
+    }
+
+    #[inline]
+    pub fn channel(&self, channel_index: usize) -> &Wavetable {
+        match &self.right {
+            Some(right) if channel_index % 2 == 1 => right,
+            _ => self,
+        }
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.frames.samples
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.frames.frame_size
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.integrals.len()
+    }
+
+    pub fn frame(&self, index: usize) -> &[f32] {
+        let index = index.min(self.frame_count() - 1);
+        &self.frames.samples[index * self.frames.frame_size..(index + 1) * self.frames.frame_size]
+    }
+
+    // Первый из двух соседних кадров и доля морфа между ними для позиции 0..1
+    #[inline]
+    fn frame_pair(&self, position: f32) -> (usize, f32) {
+        let last = self.frame_count() - 1;
+        if last == 0 || !position.is_finite() {
+            return (0, 0.0);
+        }
+        let index_f = position.clamp(0.0, 1.0) * last as f32;
+        let index = (index_f as usize).min(last - 1);
+        (index, index_f - index as f32)
+    }
+
+    #[inline]
+    pub fn lookup(&self, position: f32, x: f32, interpolation: Interpolation) -> f32 {
+        let (index, fract) = self.frame_pair(position);
+        let a = interpolation::lookup(self.frame(index), x, interpolation);
+        if fract == 0.0 {
+            return a;
+        }
+        let b = interpolation::lookup(self.frame(index + 1), x, interpolation);
+        a + (b - a) * fract
+    }
+
+    // Мип-уровни по скорости входа slope, чтобы быстрый проход по таблице не давал алиасинга
+    #[inline]
+    pub fn lookup_band_limited(
+        &self,
+        position: f32,
+        x: f32,
+        slope: f32,
+        interpolation: Interpolation,
+    ) -> f32 {
+        let (index, fract) = self.frame_pair(position);
+        let level = self.mip_level(slope);
+        let a = self.lookup_level(index, level, x, interpolation);
+        if fract == 0.0 {
+            return a;
+        }
+        let b = self.lookup_level(index + 1, level, x, interpolation);
+        a + (b - a) * fract
+    }
+
+    #[inline]
+    fn mip_level(&self, slope: f32) -> f32 {
+        // Период равен 4, так что гармоника h звучит на частоте h * slope / 4 от частоты
+        // дискретизации и не заворачивается, пока h не больше 2 / slope
+        let max_harmonic = mipmap::max_harmonic(self.frames.frame_size) as f32;
+        let level = (max_harmonic * slope / 2.0).log2();
+        // max/min вместо clamp, чтобы NaN дал уровень 0
+        level.max(0.0).min(self.frames.mip_levels as f32)
+    }
+
+    #[inline]
+    fn lookup_level(&self, frame: usize, level: f32, x: f32, interpolation: Interpolation) -> f32 {
+        let low = level as usize;
+        let fract = level - low as f32;
+        let a = interpolation::lookup(self.level(frame, low), x, interpolation);
+        if fract == 0.0 {
+            return a;
+        }
+        let b = interpolation::lookup(self.level(frame, low + 1), x, interpolation);
+        a + (b - a) * fract
+    }
+
+    // Уровень 0 - сам кадр
+    #[inline]
+    fn level(&self, frame: usize, level: usize) -> &[f32] {
+        if level == 0 {
+            return self.frame(frame);
+        }
+        let start = ((frame * self.frames.mip_levels) + level - 1) * self.frames.frame_size;
+        &self.frames.mipmaps[start..start + self.frames.frame_size]
+    }
+
+    // Морф линейный, поэтому первообразные - та же смесь первообразных обоих кадров
+    #[inline]
+    pub(crate) fn shape(&self, position: f32) -> Shape<'_> {
+        let (index, fract) = self.frame_pair(position);
+        if fract == 0.0 {
+            Shape::Custom(&self.frames.integrals[index])
+        } else {
+            Shape::Morph(
+                &self.frames.integrals[index],
+                &self.frames.integrals[index + 1],
+                fract as f64,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    fn two_frames() -> Wavetable {
+        let mut samples = vec![0.5; 8];
+        samples.extend([-0.5; 8]);
+        // Неполный третий кадр отбрасывается
+        samples.extend([1.0; 3]);
+        Wavetable::new(samples, 8)
+    }
+
+    #[test]
+    fn splits_into_whole_frames() {
+        let table = two_frames();
+        assert_eq!(table.frame_count(), 2);
+        assert_eq!(table.samples().len(), 16);
+        assert_eq!(table.frame(1), &[-0.5; 8]);
+
+        let single = Wavetable::new(vec![0.1, 0.2, 0.3], 0);
+        assert_eq!(single.frame_count(), 1);
+        assert_eq!(single.frame_size(), 3);
+        assert_eq!(Wavetable::new(Vec::new(), 2048).frame_count(), 1);
+    }
+
+    #[test]
+    fn single_frame_matches_lookup_custom() {
+        let samples = (0..64).map(|i| (i as f32 * 0.3).sin()).collect::<Vec<_>>();
+        let table = Wavetable::new(samples.clone(), 0);
+        for i in -40..40 {
+            let x = i as f32 * 0.37;
+            assert_eq!(
+                table.lookup(0.7, x, Interpolation::Linear),
+                utils::lookup_custom(&samples, x)
+            );
+        }
+    }
+
+    #[test]
+    fn position_morphs_between_frames() {
+        let table = two_frames();
+        assert_eq!(table.lookup(0.0, 1.3, Interpolation::Linear), 0.5);
+        assert_eq!(table.lookup(1.0, 1.3, Interpolation::Linear), -0.5);
+        assert!(table.lookup(0.5, 1.3, Interpolation::Linear).abs() < 1.0e-6);
+        assert!((table.lookup(0.25, 1.3, Interpolation::Linear) - 0.25).abs() < 1.0e-6);
+        // Позиция вне диапазона прижимается к крайним кадрам
+        assert_eq!(table.lookup(-3.0, 1.3, Interpolation::Linear), 0.5);
+        assert_eq!(table.lookup(f32::NAN, 1.3, Interpolation::Linear), 0.5);
+    }
+
+    #[test]
+    fn slow_input_reads_the_original_frame() {
+        let samples = (0..257)
+            .map(|i| if i < 128 { 1.0 } else { -1.0 })
+            .collect::<Vec<_>>();
+        let table = Wavetable::new(samples, 0);
+        for i in 0..100 {
+            let x = i as f32 * 0.04;
+            let expected = table.lookup(0.0, x, Interpolation::Linear);
+            for slope in [0.0, 1.0e-3, f32::NAN] {
+                let y = table.lookup_band_limited(0.0, x, slope, Interpolation::Linear);
+                assert_eq!(y, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn fast_input_reads_the_fundamental() {
+        let samples = (0..257)
+            .map(|i| if i < 128 { 1.0 } else { -1.0 })
+            .collect::<Vec<_>>();
+        let table = Wavetable::new(samples, 0);
+        // Квадрат без верхних гармоник становится синусом с амплитудой 4 / pi
+        for i in 0..100 {
+            let x = i as f32 * 0.04;
+            let y = table.lookup_band_limited(0.0, x, 10.0, Interpolation::Linear);
+            let expected = 4.0 / std::f32::consts::PI * (x * std::f32::consts::FRAC_PI_2).sin();
+            assert!((y - expected).abs() < 0.05, "{} vs {}", y, expected);
+        }
+    }
+}
@@ -3,6 +3,10 @@ use nih_plug::prelude::*;
 use parking_lot::RwLock;
 use std::sync::Arc;
 
+use crate::engine::{SmoothingSettings, Waveform};
+use crate::table_state::EmbeddedTable;
+use crate::wav_reader::{LoadOptions, Normalization, TableChannels};
+
 #[derive(Params)]
 pub struct WFParams {
     #[id = "gain"]
@@ -11,35 +15,152 @@ pub struct WFParams {
     pub phase: FloatParam,
     #[id = "drywet"]
     pub dw: FloatParam,
+    // Второй набор для нечетных (правых) каналов или для Side, когда каналы не связаны
+    #[id = "link"]
+    pub link: BoolParam,
+    #[id = "mid_side"]
+    pub mid_side: BoolParam,
+    #[id = "spread"]
+    pub spread: FloatParam,
+    #[id = "gain_r"]
+    pub gain_r: FloatParam,
+    #[id = "phase_r"]
+    pub phase_r: FloatParam,
+    #[id = "drywet_r"]
+    pub dw_r: FloatParam,
     #[id = "waveform"]
     pub waveform: IntParam,
+    // Положение морфа: 0 - синус, 1 - треугольник, 2 - пила, 3 - меандр, 4 - таблица
+    #[id = "shape"]
+    pub shape: FloatParam,
+    // Время кроссфейда при смене формы или таблицы
+    #[id = "crossfade"]
+    pub crossfade: FloatParam,
+    // Позиция внутри многокадровой таблицы, 0 - первый кадр, 1 - последний
+    #[id = "table_position"]
+    pub table_position: FloatParam,
+    // Смесь таблицы (слот A) с таблицей слота B
+    #[id = "table_morph"]
+    pub table_morph: FloatParam,
+    #[id = "interpolation"]
+    pub interpolation: IntParam,
+    // Полосоограниченные уровни таблицы при большом Drive
+    #[id = "band_limit"]
+    pub band_limit: BoolParam,
+    #[id = "anti_alias"]
+    pub anti_alias: IntParam,
+    #[id = "oversampling"]
+    pub oversampling: IntParam,
+    #[id = "os_quality"]
+    pub oversampling_quality: IntParam,
     #[persist = "waveform_path"]
     pub waveform_path: RwLock<String>,
+    // Размер кадра wavetable в отсчетах, 0 - из чанка clm или весь файл одним кадром
+    #[persist = "frame_size"]
+    pub frame_size: RwLock<usize>,
+    // Какие каналы многоканального файла берутся в таблицу
+    #[persist = "table_channels"]
+    pub table_channels: RwLock<TableChannels>,
+    // Нормализация таблицы при загрузке
+    #[persist = "normalization"]
+    pub normalization: RwLock<Normalization>,
+    // Хранить таблицу в сессии, а не только путь к файлу
+    #[persist = "embed_table"]
+    pub embed_table: RwLock<bool>,
+    #[persist = "embedded_table"]
+    pub embedded_table: RwLock<Option<EmbeddedTable>>,
+    // Файл слота B для Table Morph, загружается с теми же настройками
+    #[persist = "slot_b_path"]
+    pub slot_b_path: RwLock<String>,
+    #[persist = "embedded_slot_b"]
+    pub embedded_slot_b: RwLock<Option<EmbeddedTable>>,
+    // Выравнивать нули слота B по нулям слота A
+    #[persist = "align_crossings"]
+    pub align_crossings: RwLock<bool>,
+    // Папка, в которой ищется пропавший файл таблицы
+    #[persist = "library_root"]
+    pub library_root: RwLock<String>,
+    // Стиль и время сглаживания каждого параметра, задаются в окне Smoothing
+    #[persist = "smoothing"]
+    pub smoothing: RwLock<SmoothingSettings>,
+}
+
+impl WFParams {
+    pub fn load_options(&self) -> LoadOptions {
+        LoadOptions {
+            frame_size: *self.frame_size.read(),
+            table_channels: *self.table_channels.read(),
+            normalization: *self.normalization.read(),
+        }
+    }
+}
+
+fn gain_param(name: &str) -> FloatParam {
+    FloatParam::new(
+        name,
+        0.0,
+        FloatRange::Linear {
+            min: -60.0,
+            max: 120.0,
+        },
+    )
+    .with_unit("dB")
+}
+
+fn phase_param(name: &str) -> FloatParam {
+    FloatParam::new(
+        name,
+        0.0,
+        FloatRange::Linear {
+            min: 0.0,
+            max: 360.0,
+        },
+    )
+    .with_unit("deg")
+}
+
+fn dw_param(name: &str) -> FloatParam {
+    FloatParam::new(name, 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+}
+
+// "Triangle > Saw 40%" между двумя формами
+fn shape_label(value: f32) -> String {
+    let (a, b, t) = Waveform::morph_endpoints(value);
+    let name = |waveform| match waveform {
+        Waveform::Sine => "Sine",
+        Waveform::Triangle => "Triangle",
+        Waveform::Saw => "Saw",
+        Waveform::Square => "Square",
+        _ => "Table",
+    };
+    match t {
+        t if t < 0.005 => name(a).to_string(),
+        t if t > 0.995 => name(b).to_string(),
+        t => format!("{} > {} {:.0}%", name(a), name(b), t * 100.0),
+    }
 }
 
 impl Default for WFParams {
     fn default() -> Self {
         Self {
-            gain: FloatParam::new(
-                "Drive",
-                0.0,
-                FloatRange::Linear {
-                    min: -60.0,
-                    max: 120.0,
-                },
-            )
-            .with_unit("dB"),
-            phase: FloatParam::new(
-                "Phase",
+            gain: gain_param("Drive"),
+            phase: phase_param("Phase"),
+            dw: dw_param("Dry/Wet"),
+            link: BoolParam::new("Link Channels", true),
+            mid_side: BoolParam::new("Mid/Side", false),
+            spread: FloatParam::new(
+                "Spread",
                 0.0,
                 FloatRange::Linear {
                     min: 0.0,
-                    max: 360.0,
+                    max: 180.0,
                 },
             )
             .with_unit("deg"),
-            dw: FloatParam::new("Dry/Wet", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
-            waveform: IntParam::new("Waveform", 1, IntRange::Linear { min: 0, max: 4 })
+            gain_r: gain_param("Drive R/S"),
+            phase_r: phase_param("Phase R/S"),
+            dw_r: dw_param("Dry/Wet R/S"),
+            waveform: IntParam::new("Waveform", 1, IntRange::Linear { min: 0, max: 5 })
                 .with_value_to_string(Arc::new(|s| {
                     match s {
                         0 => "Sine",
@@ -47,11 +168,91 @@ impl Default for WFParams {
                         2 => "Saw",
                         3 => "Square",
                         4 => "Custom (file)",
+                        5 => "Shape morph",
                         _ => "How has you entered this value? (>O_o<)",
                     }
                     .to_string()
                 })),
+            shape: FloatParam::new("Shape", 0.0, FloatRange::Linear { min: 0.0, max: 4.0 })
+                .with_value_to_string(Arc::new(shape_label)),
+            crossfade: FloatParam::new(
+                "Crossfade",
+                crate::engine::DEFAULT_CROSSFADE_MS,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 500.0,
+                },
+            )
+            .with_unit("ms"),
+            table_position: FloatParam::new(
+                "Table Position",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+            table_morph: FloatParam::new(
+                "Table Morph",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ),
+            interpolation: IntParam::new("Interpolation", 1, IntRange::Linear { min: 0, max: 6 })
+                .with_value_to_string(Arc::new(|s| {
+                    match s {
+                        0 => "Nearest",
+                        1 => "Linear",
+                        2 => "Hermite",
+                        3 => "Catmull-Rom",
+                        4 => "Lagrange 4",
+                        5 => "Lagrange 6",
+                        6 => "Windowed sinc",
+                        _ => "How has you entered this value? (>O_o<)",
+                    }
+                    .to_string()
+                })),
+            band_limit: BoolParam::new("Band-limit Table", false),
+            anti_alias: IntParam::new("Anti-alias", 0, IntRange::Linear { min: 0, max: 2 })
+                .with_value_to_string(Arc::new(|s| {
+                    match s {
+                        0 => "Off",
+                        1 => "ADAA 1st order",
+                        2 => "ADAA 2nd order",
+                        _ => "How has you entered this value? (>O_o<)",
+                    }
+                    .to_string()
+                })),
+            oversampling: IntParam::new(
+                "Oversampling",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: crate::oversampling::MAX_STAGES as i32,
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{}x", 1 << s))),
+            oversampling_quality: IntParam::new(
+                "Oversampling Quality",
+                1,
+                IntRange::Linear { min: 0, max: 2 },
+            )
+            .with_value_to_string(Arc::new(|s| {
+                match s {
+                    0 => "Low",
+                    1 => "Medium",
+                    2 => "High",
+                    _ => "How has you entered this value? (>O_o<)",
+                }
+                .to_string()
+            })),
             waveform_path: RwLock::new(String::new()),
+            frame_size: RwLock::new(0),
+            table_channels: RwLock::new(TableChannels::Mix),
+            normalization: RwLock::new(Normalization::Peak),
+            embed_table: RwLock::new(true),
+            embedded_table: RwLock::new(None),
+            slot_b_path: RwLock::new(String::new()),
+            embedded_slot_b: RwLock::new(None),
+            align_crossings: RwLock::new(false),
+            library_root: RwLock::new(String::new()),
+            smoothing: RwLock::new(SmoothingSettings::default()),
         }
     }
 }
@@ -0,0 +1,111 @@
+// Чтение банков Surge (.wt): заголовок "vawt", затем кадры в f32 или i16
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+const HEADER_LEN: usize = 12;
+// Флаги заголовка Surge
+const FLAG_INT16: u16 = 0x4;
+const FLAG_INT16_FULL_RANGE: u16 = 0x8;
+
+#[derive(Debug)]
+pub struct WtFormatError(&'static str);
+
+impl fmt::Display for WtFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid .wt file: {}", self.0)
+    }
+}
+
+impl Error for WtFormatError {}
+
+// Surge .wt: все кадры подряд и размер кадра
+pub fn read_wt(path: &Path) -> Result<(Vec<f32>, usize), Box<dyn Error + Send + Sync>> {
+    let data = std::fs::read(path)?;
+    Ok(parse_wt(&data)?)
+}
+
+fn parse_wt(data: &[u8]) -> Result<(Vec<f32>, usize), WtFormatError> {
+    if data.len() < HEADER_LEN || &data[0..4] != b"vawt" {
+        return Err(WtFormatError("missing vawt header"));
+    }
+    let frame_size = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let frame_count = u16::from_le_bytes([data[8], data[9]]) as usize;
+    let flags = u16::from_le_bytes([data[10], data[11]]);
+    if frame_size == 0 || frame_count == 0 {
+        return Err(WtFormatError("no frames"));
+    }
+
+    let num_samples = frame_size * frame_count;
+    let body = &data[HEADER_LEN..];
+    let samples = if flags & FLAG_INT16 != 0 {
+        // Без флага полного диапазона Surge хранит отсчеты в 15 битах: 16384 = 1.0
+        let scale = if flags & FLAG_INT16_FULL_RANGE != 0 {
+            32768.0
+        } else {
+            16384.0
+        };
+        body.get(..num_samples * 2)
+            .ok_or(WtFormatError("file is shorter than the header says"))?
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / scale)
+            .collect()
+    } else {
+        body.get(..num_samples * 4)
+            .ok_or(WtFormatError("file is shorter than the header says"))?
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    };
+
+    Ok((samples, frame_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(frame_size: u32, frame_count: u16, flags: u16) -> Vec<u8> {
+        let mut data = b"vawt".to_vec();
+        data.extend_from_slice(&frame_size.to_le_bytes());
+        data.extend_from_slice(&frame_count.to_le_bytes());
+        data.extend_from_slice(&flags.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn reads_float_frames() {
+        let mut data = header(2, 2, 0);
+        for s in [0.5f32, -0.5, 0.25, 1.0] {
+            data.extend_from_slice(&s.to_le_bytes());
+        }
+        let (samples, frame_size) = parse_wt(&data).unwrap();
+        assert_eq!(frame_size, 2);
+        assert_eq!(samples, vec![0.5, -0.5, 0.25, 1.0]);
+    }
+
+    #[test]
+    fn scales_int16_frames() {
+        let mut data = header(2, 1, FLAG_INT16);
+        for s in [16384i16, -8192] {
+            data.extend_from_slice(&s.to_le_bytes());
+        }
+        assert_eq!(parse_wt(&data).unwrap().0, vec![1.0, -0.5]);
+
+        let mut data = header(2, 1, FLAG_INT16 | FLAG_INT16_FULL_RANGE);
+        for s in [16384i16, -32768] {
+            data.extend_from_slice(&s.to_le_bytes());
+        }
+        assert_eq!(parse_wt(&data).unwrap().0, vec![0.5, -1.0]);
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        assert!(parse_wt(b"RIFF").is_err());
+        assert!(parse_wt(&header(0, 1, 0)).is_err());
+        // Заголовок обещает 4 отсчета, а данных на один
+        let mut data = header(2, 2, 0);
+        data.extend_from_slice(&1.0f32.to_le_bytes());
+        assert!(parse_wt(&data).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-pub fn zero_crosing_points(table: &Vec<f32>, dest_vec: &mut Vec<f32>) {
+pub fn zero_crosing_points(table: &[f32], dest_vec: &mut Vec<f32>) {
 	dest_vec.clear();
 	
     if table.is_empty() {        
@@ -10,10 +10,69 @@ pub fn zero_crosing_points(table: &Vec<f32>, dest_vec: &mut Vec<f32>) {
     }
 
     for index in 0..table.len() - 1 {
-        if table[index] * table[index + 1] < 0.0 {
+        // Бесконечности дали бы NaN при интерполяции точки перехода
+        if table[index] * table[index + 1] < 0.0
+            && table[index].is_finite()
+            && table[index + 1].is_finite()
+        {
             let index_float_part =
                 table[index].abs() / (table[index].abs() + table[index + 1].abs());
             dest_vec.push((index as f32 + index_float_part) / table.len() as f32);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn empty_and_single_sample_tables_have_no_crossings() {
+        let mut points = vec![0.3];
+        zero_crosing_points(&[], &mut points);
+        assert!(points.is_empty());
+        zero_crosing_points(&[0.5], &mut points);
+        assert!(points.is_empty());
+        zero_crosing_points(&[-0.5], &mut points);
+        assert!(points.is_empty());
+    }
+
+    #[test]
+    fn finds_interpolated_crossing() {
+        let mut points = Vec::new();
+        zero_crosing_points(&[1.0, 1.0, -1.0, -1.0], &mut points);
+        assert_eq!(points.len(), 2);
+        // Между отсчетами 1 и 2 ровно посередине, плюс переход через конец таблицы
+        assert!((points[0] - 0.0).abs() < 1.0e-6);
+        assert!((points[1] - 1.5 / 4.0).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn non_finite_samples_do_not_produce_points() {
+        let mut points = Vec::new();
+        zero_crosing_points(&[f32::NAN, 1.0, f32::NAN, -1.0], &mut points);
+        assert!(points.iter().all(|p| p.is_finite()));
+        zero_crosing_points(&[f32::INFINITY, f32::NEG_INFINITY, 0.5], &mut points);
+        assert!(points.iter().all(|p| p.is_finite()));
+    }
+
+    proptest! {
+        #[test]
+        fn points_are_sorted_and_inside_the_table(table in prop::collection::vec(-1.0f32..=1.0, 0..256)) {
+            let mut points = Vec::new();
+            zero_crosing_points(&table, &mut points);
+            prop_assert!(points.iter().all(|p| (0.0..1.0).contains(p)));
+            prop_assert!(points.windows(2).all(|w| w[0] <= w[1]));
+        }
+
+        #[test]
+        fn counts_every_sign_change(table in prop::collection::vec(-1.0f32..=1.0, 1..256)) {
+            let mut points = Vec::new();
+            zero_crosing_points(&table, &mut points);
+            let wrap = usize::from(table[table.len() - 1] * table[0] < 0.0);
+            let inner = table.windows(2).filter(|w| w[0] * w[1] < 0.0).count();
+            prop_assert_eq!(points.len(), wrap + inner);
+        }
+    }
+}
@@ -0,0 +1,601 @@
+// Эталонные рендеры: сигналы сворачиваются движком и сравниваются с tests/golden.
+// Нет эталона - ошибка; новые и намеренно измененные пишутся через WAVEFOLDER_BLESS=1 cargo test
+use std::f32::consts::TAU;
+use std::path::PathBuf;
+
+use wavefolder::engine::{
+    AntiAlias, Interpolation, MORPH_SHAPES, Oversampling, OversamplingQuality, Smoothing,
+    SmoothingSettings, SmoothingStyle, TableHandoff, WaveFolderEngine, Waveform, Wavetable,
+};
+
+const SAMPLE_RATE: f32 = 48000.0;
+const SIGNAL_LEN: usize = 512;
+const TOLERANCE: f32 = 1.0e-4;
+const DRIVES_DB: [f32; 3] = [0.0, 24.0, 60.0];
+const ANTI_ALIAS_MODES: [AntiAlias; 3] = [
+    AntiAlias::Off,
+    AntiAlias::FirstOrder,
+    AntiAlias::SecondOrder,
+];
+
+fn sine_sweep() -> Vec<f32> {
+    // Экспоненциальный свип 20 Гц - 20 кГц
+    let (f0, f1) = (20.0f32, 20000.0f32);
+    let duration = SIGNAL_LEN as f32 / SAMPLE_RATE;
+    let k = (f1 / f0).ln();
+    (0..SIGNAL_LEN)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE;
+            0.8 * (TAU * f0 * duration / k * ((t / duration * k).exp() - 1.0)).sin()
+        })
+        .collect()
+}
+
+fn impulse() -> Vec<f32> {
+    let mut signal = vec![0.0; SIGNAL_LEN];
+    signal[0] = 1.0;
+    signal
+}
+
+fn noise() -> Vec<f32> {
+    // Детерминированный LCG, чтобы шум был одинаковым на всех машинах
+    let mut state = 0x1234_5678u32;
+    (0..SIGNAL_LEN)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+        })
+        .collect()
+}
+
+fn custom_table() -> Vec<f32> {
+    // Несимметричная таблица с постоянной составляющей
+    (0..256)
+        .map(|i| {
+            let t = i as f32 / 256.0 * TAU;
+            0.7 * t.sin() + 0.2 * (3.0 * t).sin() + 0.1
+        })
+        .collect()
+}
+
+fn engine(
+    waveform: Waveform,
+    anti_alias: AntiAlias,
+    oversampling: Oversampling,
+) -> WaveFolderEngine {
+    let mut engine = WaveFolderEngine::new(1, SAMPLE_RATE);
+    engine.load_table(custom_table());
+    engine.set_waveform(waveform);
+    engine.set_anti_alias(anti_alias);
+    engine.set_oversampling(oversampling);
+    engine.set_oversampling_quality(OversamplingQuality::Medium);
+    engine.set_phase(30.0);
+    engine.set_dry_wet(0.8);
+    engine
+}
+
+// Все тестовые сигналы на всех Drive подряд
+fn render_case(waveform: Waveform, anti_alias: AntiAlias, oversampling: Oversampling) -> Vec<f32> {
+    render_interpolated(waveform, anti_alias, oversampling, Interpolation::Linear)
+}
+
+fn render_interpolated(
+    waveform: Waveform,
+    anti_alias: AntiAlias,
+    oversampling: Oversampling,
+    interpolation: Interpolation,
+) -> Vec<f32> {
+    let mut output = Vec::new();
+    for signal in [sine_sweep(), impulse(), noise()] {
+        for drive in DRIVES_DB {
+            let mut engine = engine(waveform, anti_alias, oversampling);
+            engine.set_interpolation(interpolation);
+            engine.set_drive(drive);
+            engine.reset();
+
+            let mut block = signal.clone();
+            engine.process_block(&mut block);
+            output.extend_from_slice(&block);
+        }
+    }
+    output
+}
+
+fn check_golden(name: &str, output: &[f32]) {
+    assert!(
+        output.iter().all(|s| s.is_finite()),
+        "{}: output is not finite",
+        name
+    );
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.f32", name));
+
+    if std::env::var_os("WAVEFOLDER_BLESS").is_some() {
+        let bytes = output
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect::<Vec<_>>();
+        std::fs::write(&path, bytes).expect("Failed to write the reference output");
+        eprintln!("Wrote reference output {}", path.display());
+        return;
+    }
+
+    // Пропавший эталон не пересоздается сам, иначе удаленный файл молча проходит
+    let reference = std::fs::read(&path)
+        .unwrap_or_else(|err| {
+            panic!(
+                "{}: no reference output at {} ({}), write it with WAVEFOLDER_BLESS=1",
+                name,
+                path.display(),
+                err
+            )
+        })
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect::<Vec<_>>();
+    assert_eq!(reference.len(), output.len(), "{}: length changed", name);
+    for (i, (expected, actual)) in reference.iter().zip(output).enumerate() {
+        assert!(
+            (expected - actual).abs() <= TOLERANCE,
+            "{}: sample {} differs, expected {}, got {}",
+            name,
+            i,
+            expected,
+            actual
+        );
+    }
+}
+
+fn check_waveform(waveform: Waveform) {
+    for anti_alias in ANTI_ALIAS_MODES {
+        let name = format!("{:?}_{:?}", waveform, anti_alias).to_lowercase();
+        check_golden(&name, &render_case(waveform, anti_alias, Oversampling::X1));
+    }
+}
+
+#[test]
+fn golden_sine() {
+    check_waveform(Waveform::Sine);
+}
+
+#[test]
+fn golden_triangle() {
+    check_waveform(Waveform::Triangle);
+}
+
+#[test]
+fn golden_saw() {
+    check_waveform(Waveform::Saw);
+}
+
+#[test]
+fn golden_square() {
+    check_waveform(Waveform::Square);
+}
+
+#[test]
+fn golden_custom() {
+    check_waveform(Waveform::Custom);
+}
+
+#[test]
+fn golden_oversampling() {
+    for waveform in [Waveform::Triangle, Waveform::Custom] {
+        for oversampling in [Oversampling::X2, Oversampling::X4, Oversampling::X16] {
+            let name = format!("{:?}_{:?}", waveform, oversampling).to_lowercase();
+            check_golden(&name, &render_case(waveform, AntiAlias::Off, oversampling));
+        }
+    }
+}
+
+#[test]
+fn golden_interpolation() {
+    for interpolation in [
+        Interpolation::Nearest,
+        Interpolation::Hermite,
+        Interpolation::CatmullRom,
+        Interpolation::Lagrange4,
+        Interpolation::Lagrange6,
+        Interpolation::Sinc,
+    ] {
+        let name = format!("custom_{:?}", interpolation).to_lowercase();
+        let output = render_interpolated(
+            Waveform::Custom,
+            AntiAlias::Off,
+            Oversampling::X1,
+            interpolation,
+        );
+        check_golden(&name, &output);
+    }
+}
+
+#[test]
+fn golden_band_limited() {
+    let mut output = Vec::new();
+    for drive in DRIVES_DB {
+        let mut engine = engine(Waveform::Custom, AntiAlias::Off, Oversampling::X1);
+        engine.set_band_limit(true);
+        engine.set_drive(drive);
+        engine.reset();
+
+        let mut block = sine_sweep();
+        engine.process_block(&mut block);
+        output.extend_from_slice(&block);
+    }
+    check_golden("custom_band_limited", &output);
+}
+
+#[test]
+fn golden_stereo_spread_mid_side() {
+    let mut engine = WaveFolderEngine::new(2, SAMPLE_RATE);
+    engine.set_waveform(Waveform::Sine);
+    engine.set_drive(12.0);
+    engine.set_spread(90.0);
+    engine.set_linked(false);
+    engine.set_mid_side(true);
+    engine.set_second_drive(30.0);
+    engine.reset();
+
+    let mut left = sine_sweep();
+    let mut right = noise();
+    engine.process(&mut [left.as_mut_slice(), right.as_mut_slice()]);
+
+    let mut output = left;
+    output.extend_from_slice(&right);
+    check_golden("stereo_spread_mid_side", &output);
+}
+
+#[test]
+fn mid_side_ignores_link() {
+    let render = |linked| {
+        let mut engine = WaveFolderEngine::new(2, SAMPLE_RATE);
+        engine.set_waveform(Waveform::Sine);
+        engine.set_linked(linked);
+        engine.set_mid_side(true);
+        engine.set_drive(12.0);
+        engine.set_second_drive(30.0);
+        engine.set_second_phase(45.0);
+        engine.set_second_dry_wet(0.5);
+        engine.reset();
+
+        let mut left = sine_sweep();
+        let mut right = noise();
+        engine.process(&mut [left.as_mut_slice(), right.as_mut_slice()]);
+        left.extend_from_slice(&right);
+        left
+    };
+    // S складывается со вторым набором и при связанных каналах
+    assert_eq!(render(true), render(false));
+}
+
+#[test]
+fn spread_in_mid_side_offsets_mid_and_side() {
+    let render = |spread, mid_phase, side_phase| {
+        let mut engine = WaveFolderEngine::new(2, SAMPLE_RATE);
+        engine.set_waveform(Waveform::Sine);
+        engine.set_mid_side(true);
+        engine.set_drive(12.0);
+        engine.set_second_drive(12.0);
+        engine.set_spread(spread);
+        engine.set_phase(mid_phase);
+        engine.set_second_phase(side_phase);
+        engine.reset();
+
+        let mut left = sine_sweep();
+        let mut right = noise();
+        engine.process(&mut [left.as_mut_slice(), right.as_mut_slice()]);
+        left.extend_from_slice(&right);
+        left
+    };
+    // В режиме Mid/Side разнос сдвигает M назад и S вперед на половину своего значения
+    let spread = render(40.0, 0.0, 0.0);
+    let offsets = render(0.0, -20.0, 20.0);
+    for (s, o) in spread.iter().zip(&offsets) {
+        assert!((s - o).abs() < TOLERANCE, "{} vs {}", s, o);
+    }
+}
+
+#[test]
+fn nan_input_stays_finite() {
+    for anti_alias in ANTI_ALIAS_MODES {
+        let mut engine = engine(Waveform::Sine, anti_alias, Oversampling::X4);
+        engine.reset();
+
+        let mut block = noise();
+        block[10] = f32::NAN;
+        engine.process_block(&mut block);
+        assert!(block.iter().all(|s| s.is_finite()), "{:?}", anti_alias);
+    }
+}
+
+#[test]
+fn stereo_table_folds_each_channel_with_its_own_table() {
+    let inverted = custom_table().iter().map(|s| -s).collect::<Vec<_>>();
+    for anti_alias in ANTI_ALIAS_MODES {
+        let mut engine = WaveFolderEngine::new(2, SAMPLE_RATE);
+        engine.load_wavetable(
+            Wavetable::new(custom_table(), 0).with_right(Wavetable::new(inverted.clone(), 0)),
+        );
+        engine.set_waveform(Waveform::Custom);
+        engine.set_anti_alias(anti_alias);
+        engine.set_drive(24.0);
+        engine.reset();
+
+        // Сухой сигнал выключен, так что правый канал - это левый с обратным знаком
+        let mut left = sine_sweep();
+        let mut right = sine_sweep();
+        engine.process(&mut [left.as_mut_slice(), right.as_mut_slice()]);
+        for (l, r) in left.iter().zip(&right) {
+            assert!(
+                (l + r).abs() < TOLERANCE,
+                "{:?}: {} vs {}",
+                anti_alias,
+                l,
+                r
+            );
+        }
+    }
+}
+
+// Медленный синус: большой скачок на выходе может дать только смена формы
+fn slow_sine() -> Vec<f32> {
+    (0..3 * SIGNAL_LEN)
+        .map(|i| 0.3 + 0.2 * (TAU * 50.0 * i as f32 / SAMPLE_RATE).sin())
+        .collect()
+}
+
+fn max_step(output: &[f32]) -> f32 {
+    // Первые отсчеты пропускаем: ADAA начинает с нулевой истории
+    output[4..]
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).abs())
+        .fold(0.0, f32::max)
+}
+
+fn largest_step(engine: &mut WaveFolderEngine, switch: impl FnOnce(&mut WaveFolderEngine)) -> f32 {
+    let mut signal = slow_sine();
+    let (before, after) = signal.split_at_mut(SIGNAL_LEN);
+    engine.process_block(before);
+    switch(engine);
+    engine.process_block(after);
+    max_step(&signal)
+}
+
+#[test]
+fn waveform_change_crossfades() {
+    for anti_alias in ANTI_ALIAS_MODES {
+        for (crossfade_ms, max_step) in [(10.0, 0.01), (0.0, f32::INFINITY)] {
+            let mut engine = WaveFolderEngine::new(1, SAMPLE_RATE);
+            engine.set_waveform(Waveform::Sine);
+            engine.set_anti_alias(anti_alias);
+            engine.set_crossfade_time(crossfade_ms);
+            engine.reset();
+
+            let step = largest_step(&mut engine, |engine| engine.set_waveform(Waveform::Square));
+            assert!(step < max_step, "{:?}: step {}", anti_alias, step);
+            // Без кроссфейда форма переключается сразу: sin(0.3 * pi / 2) -> 1 около начала
+            if crossfade_ms == 0.0 {
+                assert!(step > 0.5, "{:?}: step {}", anti_alias, step);
+            }
+        }
+    }
+}
+
+#[test]
+fn table_change_crossfades() {
+    let inverted = custom_table().iter().map(|s| -s).collect::<Vec<_>>();
+    for anti_alias in ANTI_ALIAS_MODES {
+        let handoff = TableHandoff::new(Wavetable::new(inverted.clone(), 0));
+        let mut engine = WaveFolderEngine::new(1, SAMPLE_RATE);
+        engine.load_table(custom_table());
+        engine.set_waveform(Waveform::Custom);
+        engine.set_anti_alias(anti_alias);
+        engine.set_crossfade_time(10.0);
+        engine.reset();
+
+        let step = largest_step(&mut engine, |engine| {
+            assert!(engine.receive_table(&handoff));
+        });
+        assert!(step < 0.01, "{:?}: step {}", anti_alias, step);
+
+        // После кроссфейда звучит только новая таблица; первые отсчеты еще помнят прежний вход
+        let mut tail = vec![0.3; 8];
+        engine.process_block(&mut tail);
+        let mut reference = WaveFolderEngine::new(1, SAMPLE_RATE);
+        reference.load_table(inverted.clone());
+        reference.set_waveform(Waveform::Custom);
+        reference.set_anti_alias(anti_alias);
+        reference.reset();
+        let mut expected = vec![0.3; 8];
+        reference.process_block(&mut expected);
+        for (s, e) in tail.iter().zip(&expected).skip(4) {
+            assert!(
+                (s - e).abs() < TOLERANCE,
+                "{:?}: {} vs {}",
+                anti_alias,
+                s,
+                e
+            );
+        }
+    }
+}
+
+#[test]
+fn changes_during_a_crossfade_wait_for_it() {
+    for anti_alias in ANTI_ALIAS_MODES {
+        let handoff = TableHandoff::new(Wavetable::new(custom_table(), 0));
+        let mut engine = WaveFolderEngine::new(1, SAMPLE_RATE);
+        engine.set_waveform(Waveform::Sine);
+        engine.set_anti_alias(anti_alias);
+        engine.set_crossfade_time(10.0);
+        engine.reset();
+
+        // 10 мс - 7.5 блоков; вторая смена формы и новая таблица приходят посреди кроссфейда
+        let mut signal = slow_sine();
+        for (block, chunk) in signal.chunks_mut(64).enumerate() {
+            match block {
+                4 => engine.set_waveform(Waveform::Square),
+                7 => {
+                    engine.set_waveform(Waveform::Saw);
+                    assert!(!engine.receive_table(&handoff), "{:?}", anti_alias);
+                }
+                21 => assert!(engine.receive_table(&handoff), "{:?}", anti_alias),
+                _ => {}
+            }
+            engine.process_block(chunk);
+        }
+        let step = max_step(&signal);
+        assert!(step < 0.01, "{:?}: step {}", anti_alias, step);
+
+        // В конце звучит последняя выбранная форма
+        let mut tail = vec![0.3; 8];
+        engine.process_block(&mut tail);
+        let mut reference = WaveFolderEngine::new(1, SAMPLE_RATE);
+        reference.set_waveform(Waveform::Saw);
+        reference.set_anti_alias(anti_alias);
+        reference.reset();
+        let mut expected = vec![0.3; 8];
+        reference.process_block(&mut expected);
+        for (s, e) in tail.iter().zip(&expected).skip(4) {
+            assert!(
+                (s - e).abs() < TOLERANCE,
+                "{:?}: {} vs {}",
+                anti_alias,
+                s,
+                e
+            );
+        }
+    }
+}
+
+fn render_morph(waveform: Waveform, morph: f32, anti_alias: AntiAlias) -> Vec<f32> {
+    let mut engine = engine(waveform, anti_alias, Oversampling::X1);
+    engine.set_morph(morph);
+    engine.set_drive(24.0);
+    engine.reset();
+    let mut block = sine_sweep();
+    engine.process_block(&mut block);
+    block
+}
+
+#[test]
+fn morph_blends_neighbouring_shapes() {
+    for anti_alias in ANTI_ALIAS_MODES {
+        // В целых точках морф совпадает с самой формой
+        for (index, waveform) in MORPH_SHAPES.into_iter().enumerate() {
+            let morph = render_morph(Waveform::Morph, index as f32, anti_alias);
+            let expected = render_morph(waveform, 0.0, anti_alias);
+            for (m, e) in morph.iter().zip(&expected) {
+                assert!(
+                    (m - e).abs() < TOLERANCE,
+                    "{:?} {:?}: {} vs {}",
+                    anti_alias,
+                    waveform,
+                    m,
+                    e
+                );
+            }
+        }
+
+        // Между ними - полусумма соседей, ADAA линейна по форме
+        let morph = render_morph(Waveform::Morph, 3.5, anti_alias);
+        let square = render_morph(Waveform::Square, 0.0, anti_alias);
+        let custom = render_morph(Waveform::Custom, 0.0, anti_alias);
+        for ((m, s), c) in morph.iter().zip(&square).zip(&custom) {
+            assert!(
+                (m - (s + c) / 2.0).abs() < TOLERANCE,
+                "{:?}: {} vs {} and {}",
+                anti_alias,
+                m,
+                s,
+                c
+            );
+        }
+    }
+}
+
+fn slot_b_table() -> Vec<f32> {
+    // Другая длина, чтобы слоты не совпадали по отсчетам
+    (0..128)
+        .map(|i| {
+            let t = i as f32 / 128.0 * TAU;
+            0.5 * t.cos() - 0.4 * (2.0 * t).sin()
+        })
+        .collect()
+}
+
+fn render_table(table: Wavetable, table_morph: f32, anti_alias: AntiAlias) -> Vec<f32> {
+    let mut engine = engine(Waveform::Custom, anti_alias, Oversampling::X1);
+    engine.load_wavetable(table);
+    engine.set_table_morph(table_morph);
+    engine.set_drive(24.0);
+    engine.reset();
+    let mut block = sine_sweep();
+    engine.process_block(&mut block);
+    block
+}
+
+#[test]
+fn table_morph_blends_slot_b() {
+    let with_slot_b =
+        || Wavetable::new(custom_table(), 0).with_slot_b(Some(Wavetable::new(slot_b_table(), 0)));
+    for anti_alias in ANTI_ALIAS_MODES {
+        let a = render_table(Wavetable::new(custom_table(), 0), 0.0, anti_alias);
+        let b = render_table(Wavetable::new(slot_b_table(), 0), 0.0, anti_alias);
+        for (table_morph, expected) in [
+            (0.0, a.clone()),
+            (1.0, b.clone()),
+            (0.5, a.iter().zip(&b).map(|(a, b)| (a + b) / 2.0).collect()),
+        ] {
+            let output = render_table(with_slot_b(), table_morph, anti_alias);
+            for (s, e) in output.iter().zip(&expected) {
+                assert!(
+                    (s - e).abs() < TOLERANCE,
+                    "{:?} at {}: {} vs {}",
+                    anti_alias,
+                    table_morph,
+                    s,
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn drive_is_smoothed_in_db() {
+    // 10 мс при 48 кГц - 480 отсчетов; смотрим на середину перехода от 0 до 40 дБ
+    for (style, expected_db) in [
+        (SmoothingStyle::Linear, 20.0),
+        (SmoothingStyle::Exponential, 40.0 * 0.99),
+    ] {
+        let mut engine = WaveFolderEngine::new(1, SAMPLE_RATE);
+        engine.set_waveform(Waveform::Sine);
+        engine.set_smoothing(SmoothingSettings {
+            drive: Smoothing {
+                style,
+                time_ms: 10.0,
+            },
+            ..SmoothingSettings::default()
+        });
+        engine.reset();
+        engine.set_drive(40.0);
+
+        let input = 0.001;
+        let mut block = vec![input; 480];
+        engine.process_block(&mut block);
+        let gain = 10.0f32.powf(expected_db / 20.0);
+        let expected = (gain * input * TAU / 4.0).sin();
+        assert!(
+            (block[239] - expected).abs() < expected * 1.0e-3,
+            "{:?}: {} vs {}",
+            style,
+            block[239],
+            expected
+        );
+        assert_eq!(block[479], (100.0 * input * TAU / 4.0).sin());
+    }
+}
@@ -0,0 +1,193 @@
+// Аудиопуть не должен выделять и освобождать память, как в assert_process_allocs из nih-plug,
+// в том числе пока таблицы перезагружаются
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use wavefolder::engine::{
+    AntiAlias, Oversampling, Smoothing, SmoothingSettings, SmoothingStyle, TableHandoff,
+    WaveFolderEngine, Waveform, Wavetable,
+};
+
+const SAMPLE_RATE: f32 = 48000.0;
+const BLOCK_LEN: usize = 256;
+
+struct AllocChecker;
+
+thread_local! {
+    static FORBIDDEN: Cell<bool> = const { Cell::new(false) };
+    static VIOLATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+// Паниковать внутри аллокатора нельзя, поэтому нарушения только считаются
+fn record() {
+    let _ = FORBIDDEN.try_with(|forbidden| {
+        if forbidden.get() {
+            VIOLATIONS.with(|v| v.set(v.get() + 1));
+        }
+    });
+}
+
+unsafe impl GlobalAlloc for AllocChecker {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record();
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record();
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record();
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: AllocChecker = AllocChecker;
+
+// Падает, если f выделил или освободил память в этом потоке
+fn assert_no_alloc<T>(f: impl FnOnce() -> T) -> T {
+    VIOLATIONS.with(|v| v.set(0));
+    FORBIDDEN.with(|forbidden| forbidden.set(true));
+    let result = f();
+    FORBIDDEN.with(|forbidden| forbidden.set(false));
+    let violations = VIOLATIONS.with(Cell::get);
+    assert_eq!(
+        violations, 0,
+        "{} allocations on the audio path",
+        violations
+    );
+    result
+}
+
+fn wavetable(seed: usize) -> Wavetable {
+    // Разные размеры кадров и число кадров, чтобы менялась и раскладка, и мип-уровни
+    let frame_size = [256, 512, 2048][seed % 3];
+    let frames = 1 + seed % 4;
+    let samples = (0..frame_size * frames)
+        .map(|i| ((i * (seed + 1)) as f32 * 0.01).sin())
+        .collect::<Vec<_>>();
+    Wavetable::new(samples, frame_size)
+}
+
+fn signal(offset: usize) -> Vec<f32> {
+    (0..BLOCK_LEN)
+        .map(|i| 0.8 * ((offset + i) as f32 * 0.05).sin())
+        .collect()
+}
+
+#[test]
+fn process_does_not_allocate() {
+    for waveform in [Waveform::Sine, Waveform::Custom, Waveform::Morph] {
+        for anti_alias in [
+            AntiAlias::Off,
+            AntiAlias::FirstOrder,
+            AntiAlias::SecondOrder,
+        ] {
+            for band_limit in [false, true] {
+                let mut engine = WaveFolderEngine::new(2, SAMPLE_RATE);
+                engine.load_wavetable(wavetable(5).with_slot_b(Some(wavetable(2))));
+                engine.set_waveform(waveform);
+                engine.set_anti_alias(anti_alias);
+                engine.set_band_limit(band_limit);
+                engine.set_oversampling(Oversampling::X4);
+                engine.reset();
+
+                let mut left = signal(0);
+                let mut right = signal(7);
+                assert_no_alloc(|| {
+                    for block in 0..8 {
+                        engine.set_drive(block as f32 * 10.0);
+                        engine.set_table_position(block as f32 / 8.0);
+                        engine.set_morph(block as f32 / 2.0);
+                        engine.set_table_morph(block as f32 / 8.0);
+                        // Смена стиля сглаживания посреди обработки
+                        if block == 4 {
+                            engine.set_smoothing(SmoothingSettings {
+                                drive: Smoothing {
+                                    style: SmoothingStyle::Logarithmic,
+                                    time_ms: 5.0,
+                                },
+                                phase: Smoothing {
+                                    style: SmoothingStyle::Exponential,
+                                    time_ms: 20.0,
+                                },
+                                ..SmoothingSettings::default()
+                            });
+                        }
+                        // Смена формы запускает кроссфейд со второй историей ADAA
+                        engine.set_waveform(if block % 3 == 2 {
+                            Waveform::Saw
+                        } else {
+                            waveform
+                        });
+                        engine.process(&mut [left.as_mut_slice(), right.as_mut_slice()]);
+                    }
+                });
+            }
+        }
+    }
+}
+
+#[test]
+fn table_reloads_do_not_allocate_on_the_audio_thread() {
+    let handoff = Arc::new(TableHandoff::new(wavetable(0)));
+    let done = Arc::new(AtomicBool::new(false));
+
+    // Загрузчик публикует таблицы, пока аудиопоток их забирает
+    let loader = {
+        let handoff = handoff.clone();
+        let done = done.clone();
+        std::thread::spawn(move || {
+            for seed in 1..=60 {
+                handoff.publish(wavetable(seed));
+                // Слот B меняется реже, иногда с выравниванием по нулям
+                if seed % 10 == 0 {
+                    handoff.publish_slot_b(Some(Arc::new(wavetable(seed + 1))), seed % 20 == 0);
+                }
+            }
+            done.store(true, Ordering::Release);
+        })
+    };
+
+    let mut engine = WaveFolderEngine::new(2, SAMPLE_RATE);
+    engine.set_waveform(Waveform::Custom);
+    engine.set_band_limit(true);
+    engine.set_drive(24.0);
+    engine.set_table_morph(0.5);
+    engine.reset();
+
+    let mut received = 0;
+    let mut offset = 0;
+    let mut blocks_after_finish = 0;
+    loop {
+        let finished = done.load(Ordering::Acquire);
+        if finished {
+            // Последнюю таблицу аудиопоток получает после сборки вытесненной и конца кроссфейда
+            handoff.collect_retired();
+            blocks_after_finish += 1;
+            assert!(blocks_after_finish < 100);
+        }
+        let mut left = signal(offset);
+        let mut right = signal(offset + 3);
+        assert_no_alloc(|| {
+            if engine.receive_table(&handoff) {
+                received += 1;
+            }
+            engine.process(&mut [left.as_mut_slice(), right.as_mut_slice()]);
+        });
+        assert!(left.iter().chain(&right).all(|s| s.is_finite()));
+        offset += BLOCK_LEN;
+
+        if finished && engine.table().samples() == wavetable(60).samples() {
+            break;
+        }
+    }
+    loader.join().unwrap();
+
+    assert!(received > 0);
+}